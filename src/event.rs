@@ -1,11 +1,137 @@
+use core::mem;
+use std::path::PathBuf;
+
+const KEY_COUNT: usize = mem::variant_count::<Key>();
+const BUTTON_COUNT: usize = mem::variant_count::<Button>();
+const GAMEPAD_BUTTON_COUNT: usize = mem::variant_count::<GamepadButton>();
+const GAMEPAD_AXIS_COUNT: usize = mem::variant_count::<GamepadAxis>();
+
 pub enum Event {
     Idle,
     Input(Input),
+    Resized([u32; 2]),
+    Visibility(bool),
+    Focus(bool),
+    DragEntered,
+    DragLeft,
+    #[allow(unused)]
+    FileDropped(PathBuf),
+    ScaleFactorChanged(f64),
+    GamepadConnected,
+    GamepadDisconnected,
 }
 
-#[derive(Eq, PartialEq)]
+#[derive(PartialEq)]
 pub enum Input {
     Close,
+    /// Keys are known by what the layout says they are (`symbol`) and by where they are on the
+    /// keyboard (`position`), named as in a US layout. Bindings may target either of them.
+    Key {
+        symbol: Option<Key>,
+        position: Option<Key>,
+        pressed: bool,
+
+        /// Set on presses produced by holding the key down. In bindings, whether those trigger the
+        /// action as well.
+        repeat: bool,
+    },
+    MouseButton {
+        button: Button,
+        pressed: bool,
+        position: [i32; 2],
+    },
+    MouseMotion {
+        position: [i32; 2],
+    },
+    MouseWheel {
+        delta: [i32; 2],
+    },
+    MouseDelta {
+        delta: [i32; 2],
+    },
+    Text(String),
+    GamepadButton {
+        button: GamepadButton,
+        pressed: bool,
+    },
+
+    /// Sticks go from -1 to 1, with down and right being positive. Triggers go from 0 to 1.
+    GamepadAxis {
+        axis: GamepadAxis,
+        value: f32,
+    },
+}
+
+#[rustfmt::skip]
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub enum Key {
+    A, B, C, D, E, F, G, H, I, J, K, L, M,
+    N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+
+    Digit0, Digit1, Digit2, Digit3, Digit4,
+    Digit5, Digit6, Digit7, Digit8, Digit9,
+
+    F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+
+    Escape, Tab, CapsLock, Space, Enter, Backspace,
+    Insert, Delete, Home, End, PageUp, PageDown,
+    Left, Right, Up, Down,
+
+    LeftShift, RightShift, LeftControl, RightControl,
+    LeftAlt, RightAlt, LeftSuper, RightSuper, Menu,
+
+    Minus, Equal, LeftBracket, RightBracket, Backslash,
+    Semicolon, Apostrophe, Grave, Comma, Period, Slash,
+
+    PrintScreen, ScrollLock, Pause, NumLock,
+
+    Numpad0, Numpad1, Numpad2, Numpad3, Numpad4,
+    Numpad5, Numpad6, Numpad7, Numpad8, Numpad9,
+    NumpadAdd, NumpadSubtract, NumpadMultiply, NumpadDivide,
+    NumpadDecimal, NumpadEnter,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub enum Button {
+    Left,
+    Middle,
+    Right,
+    Back,
+    Forward,
+}
+
+/// Face buttons are named after where they are, labels change from one brand to another.
+#[allow(unused)]
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub enum GamepadButton {
+    South,
+    East,
+    West,
+    North,
+    LeftBumper,
+    RightBumper,
+    LeftTrigger,
+    RightTrigger,
+    Select,
+    Start,
+    Mode,
+    LeftStick,
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+#[allow(unused)]
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub enum GamepadAxis {
+    LeftX,
+    LeftY,
+    RightX,
+    RightY,
+    LeftTrigger,
+    RightTrigger,
 }
 
 #[derive(Copy, Clone, Default)]
@@ -16,13 +142,62 @@ pub enum Action {
     Idle,
     Exit,
 
+    #[allow(unused)]
+    Resize([u32; 2]),
+    #[allow(unused)]
+    Rescale(f64),
+    Suspend,
+    Resume,
+    Focus,
+    Unfocus,
+    ToggleFullscreen,
+
+    // Pointer-driven actions receive the coordinates of the input that triggered them.
+    #[allow(unused)]
+    Point([i32; 2]),
+    #[allow(unused)]
+    Scroll([i32; 2]),
+    #[allow(unused)]
+    Look([i32; 2]),
+
+    // Axis-driven actions receive the position of the axis.
+    #[allow(unused)]
+    Steer(f32),
+
     #[allow(unused)]
     Debug(&'static str),
 }
 
-#[derive(Default)]
+impl Action {
+    fn with_vector(self, vector: [i32; 2]) -> Self {
+        match self {
+            Self::Point(_) => Self::Point(vector),
+            Self::Scroll(_) => Self::Scroll(vector),
+            Self::Look(_) => Self::Look(vector),
+            _ => self,
+        }
+    }
+
+    fn with_value(self, value: f32) -> Self {
+        match self {
+            Self::Steer(_) => Self::Steer(value),
+            _ => self,
+        }
+    }
+}
+
 pub struct EventHandler {
     on_close: Action,
+    on_symbol: KeyBindings,
+    on_position: KeyBindings,
+    on_mouse_press: [Action; BUTTON_COUNT],
+    on_mouse_release: [Action; BUTTON_COUNT],
+    on_mouse_motion: Action,
+    on_mouse_wheel: Action,
+    on_mouse_delta: Action,
+    on_gamepad_press: [Action; GAMEPAD_BUTTON_COUNT],
+    on_gamepad_release: [Action; GAMEPAD_BUTTON_COUNT],
+    on_gamepad_axis: [Action; GAMEPAD_AXIS_COUNT],
 }
 
 impl EventHandler {
@@ -30,12 +205,91 @@ impl EventHandler {
         match event {
             Event::Idle => Action::Idle,
             Event::Input(input) => self.handle_input(input),
+            Event::Resized(size) => Action::Resize(size),
+            Event::ScaleFactorChanged(scale_factor) => Action::Rescale(scale_factor),
+            Event::Visibility(false) => Action::Suspend,
+            Event::Visibility(true) => Action::Resume,
+            Event::Focus(true) => Action::Focus,
+            Event::Focus(false) => Action::Unfocus,
+
+            // Whoever wants dropped files has to look at the event itself, paths can't be bound.
+            Event::DragEntered | Event::DragLeft | Event::FileDropped(_) => Action::Nop,
+
+            // Any gamepad can drive the bindings, telling them apart is up to the caller.
+            Event::GamepadConnected | Event::GamepadDisconnected => Action::Nop,
         }
     }
 
     fn handle_input(&self, input: Input) -> Action {
         match input {
             Input::Close => self.on_close,
+            Input::Key {
+                symbol,
+                position,
+                pressed,
+                repeat,
+            } => {
+                let by_position = position.map(|key| self.on_position.get(key, pressed, repeat));
+                let by_symbol = symbol.map(|key| self.on_symbol.get(key, pressed, repeat));
+
+                // Bindings to the position win, they are the ones that survive a layout change.
+                [by_position, by_symbol]
+                    .into_iter()
+                    .flatten()
+                    .find(|action| !matches!(action, Action::Nop))
+                    .unwrap_or_default()
+            }
+
+            Input::MouseButton {
+                button,
+                pressed,
+                position,
+            } => {
+                let actions = match pressed {
+                    true => &self.on_mouse_press,
+                    false => &self.on_mouse_release,
+                };
+
+                actions[button as usize].with_vector(position)
+            }
+
+            Input::MouseMotion { position } => self.on_mouse_motion.with_vector(position),
+            Input::MouseWheel { delta } => self.on_mouse_wheel.with_vector(delta),
+            Input::MouseDelta { delta } => self.on_mouse_delta.with_vector(delta),
+
+            // Text goes straight to whatever has the keyboard focus, there is nothing to bind.
+            Input::Text(_) => Action::Nop,
+
+            Input::GamepadButton { button, pressed } => {
+                let actions = match pressed {
+                    true => &self.on_gamepad_press,
+                    false => &self.on_gamepad_release,
+                };
+
+                actions[button as usize]
+            }
+
+            Input::GamepadAxis { axis, value } => {
+                self.on_gamepad_axis[axis as usize].with_value(value)
+            }
+        }
+    }
+}
+
+impl Default for EventHandler {
+    fn default() -> Self {
+        Self {
+            on_close: Default::default(),
+            on_symbol: Default::default(),
+            on_position: Default::default(),
+            on_mouse_press: [Default::default(); BUTTON_COUNT],
+            on_mouse_release: [Default::default(); BUTTON_COUNT],
+            on_mouse_motion: Default::default(),
+            on_mouse_wheel: Default::default(),
+            on_mouse_delta: Default::default(),
+            on_gamepad_press: [Default::default(); GAMEPAD_BUTTON_COUNT],
+            on_gamepad_release: [Default::default(); GAMEPAD_BUTTON_COUNT],
+            on_gamepad_axis: [Default::default(); GAMEPAD_AXIS_COUNT],
         }
     }
 }
@@ -50,9 +304,89 @@ impl FromIterator<(Input, Action)> for EventHandler {
         for (input, action) in iter {
             match input {
                 Input::Close => ego.on_close = action,
+                Input::Key {
+                    symbol,
+                    position,
+                    pressed,
+                    repeat,
+                } => {
+                    if let Some(key) = symbol {
+                        ego.on_symbol.bind(key, pressed, repeat, action);
+                    }
+
+                    if let Some(key) = position {
+                        ego.on_position.bind(key, pressed, repeat, action);
+                    }
+                }
+
+                Input::MouseButton {
+                    button,
+                    pressed: true,
+                    ..
+                } => ego.on_mouse_press[button as usize] = action,
+
+                Input::MouseButton {
+                    button,
+                    pressed: false,
+                    ..
+                } => ego.on_mouse_release[button as usize] = action,
+
+                Input::MouseMotion { .. } => ego.on_mouse_motion = action,
+                Input::MouseWheel { .. } => ego.on_mouse_wheel = action,
+                Input::MouseDelta { .. } => ego.on_mouse_delta = action,
+                Input::Text(_) => {}
+
+                Input::GamepadButton {
+                    button,
+                    pressed: true,
+                } => ego.on_gamepad_press[button as usize] = action,
+
+                Input::GamepadButton {
+                    button,
+                    pressed: false,
+                } => ego.on_gamepad_release[button as usize] = action,
+
+                Input::GamepadAxis { axis, .. } => ego.on_gamepad_axis[axis as usize] = action,
             }
         }
 
         ego
     }
 }
+
+struct KeyBindings {
+    on_press: [Action; KEY_COUNT],
+    on_release: [Action; KEY_COUNT],
+    repeat_press: [bool; KEY_COUNT],
+}
+
+impl KeyBindings {
+    fn get(&self, key: Key, pressed: bool, repeat: bool) -> Action {
+        match (pressed, repeat) {
+            (true, true) if !self.repeat_press[key as usize] => Action::Nop,
+            (true, _) => self.on_press[key as usize],
+            (false, _) => self.on_release[key as usize],
+        }
+    }
+
+    fn bind(&mut self, key: Key, pressed: bool, repeat: bool, action: Action) {
+        match pressed {
+            true => {
+                self.on_press[key as usize] = action;
+                self.repeat_press[key as usize] = repeat;
+            }
+
+            false => self.on_release[key as usize] = action,
+        }
+    }
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            on_press: [Default::default(); KEY_COUNT],
+            on_release: [Default::default(); KEY_COUNT],
+            repeat_press: [false; KEY_COUNT],
+        }
+    }
+}
@@ -0,0 +1,580 @@
+use core::{
+    array,
+    cell::{Cell, RefCell},
+    ffi::{c_int, c_ulong},
+    iter, mem, ptr,
+};
+use std::{
+    collections::VecDeque,
+    ffi::OsStr,
+    fs::{self, File, OpenOptions},
+    io::{ErrorKind, Read},
+    os::{
+        fd::{AsRawFd, FromRawFd},
+        unix::{ffi::OsStrExt, fs::OpenOptionsExt},
+    },
+    path::{Path, PathBuf},
+};
+
+use super::GamepadId;
+use crate::event::{Event, GamepadAxis, GamepadButton, Input};
+
+const INPUT_DIR: &str = "/dev/input";
+const EVENT_SIZE: usize = mem::size_of::<libc::input_event>();
+
+const EV_KEY: u16 = 0x01;
+const EV_ABS: u16 = 0x03;
+
+const BTN_SOUTH: u16 = 0x130;
+const BTN_EAST: u16 = 0x131;
+const BTN_NORTH: u16 = 0x133;
+const BTN_WEST: u16 = 0x134;
+const BTN_TL: u16 = 0x136;
+const BTN_TR: u16 = 0x137;
+const BTN_TL2: u16 = 0x138;
+const BTN_TR2: u16 = 0x139;
+const BTN_SELECT: u16 = 0x13a;
+const BTN_START: u16 = 0x13b;
+const BTN_MODE: u16 = 0x13c;
+const BTN_THUMBL: u16 = 0x13d;
+const BTN_THUMBR: u16 = 0x13e;
+const BTN_DPAD_UP: u16 = 0x220;
+const BTN_DPAD_DOWN: u16 = 0x221;
+const BTN_DPAD_LEFT: u16 = 0x222;
+const BTN_DPAD_RIGHT: u16 = 0x223;
+
+const ABS_X: u16 = 0x00;
+const ABS_Y: u16 = 0x01;
+const ABS_Z: u16 = 0x02;
+const ABS_RX: u16 = 0x03;
+const ABS_RY: u16 = 0x04;
+const ABS_RZ: u16 = 0x05;
+const ABS_HAT0X: u16 = 0x10;
+const ABS_HAT0Y: u16 = 0x11;
+
+/// Every evdev device with gamepad buttons, including the ones plugged in later on.
+pub struct Gamepads {
+    watch: Option<File>,
+    devices: RefCell<Vec<Device>>,
+    next_id: Cell<u32>,
+    pending: RefCell<VecDeque<(GamepadId, Event)>>,
+}
+
+struct Device {
+    id: GamepadId,
+    path: PathBuf,
+    file: File,
+    state: State,
+}
+
+/// What is needed to make sense of the events of a gamepad.
+struct State {
+    axes: [Option<libc::input_absinfo>; libc::ABS_CNT],
+    hat: [i32; 2],
+}
+
+impl Gamepads {
+    pub fn new() -> Self {
+        // Watch before looking around, so that nothing plugged in meanwhile is missed.
+        let gamepads = Self {
+            watch: unsafe { watch_devices() },
+            devices: Default::default(),
+            next_id: 0.into(),
+            pending: Default::default(),
+        };
+
+        for entry in fs::read_dir(INPUT_DIR).into_iter().flatten().flatten() {
+            gamepads.open(&entry.path());
+        }
+
+        gamepads
+    }
+
+    /// Drain the events that are ready without blocking, connections included.
+    pub fn poll_events(&self) -> impl Iterator<Item = (GamepadId, Event)> + '_ {
+        // Unplugged gamepads are forgotten first, their paths may have been taken over already.
+        self.read_devices();
+        self.read_hotplug();
+
+        iter::from_fn(|| self.pending.borrow_mut().pop_front())
+    }
+
+    fn read_hotplug(&self) {
+        let Some(mut watch) = self.watch.as_ref() else {
+            return;
+        };
+
+        let header_size = mem::size_of::<libc::inotify_event>();
+        let mut buffer = [0_u8; 4_096];
+
+        while let Ok(length @ 1..) = watch.read(&mut buffer) {
+            let mut rest = &buffer[..length];
+
+            while rest.len() >= header_size {
+                let header =
+                    unsafe { ptr::read_unaligned(rest.as_ptr() as *const libc::inotify_event) };
+                let end = header_size + header.len as usize;
+
+                // Names are padded with NULs up to the length of the record.
+                let name = rest.get(header_size..end).unwrap_or_default();
+                let name = name.split(|&byte| byte == 0).next().unwrap_or_default();
+
+                if name.starts_with(b"event") {
+                    self.open(&Path::new(INPUT_DIR).join(OsStr::from_bytes(name)));
+                }
+
+                rest = rest.get(end..).unwrap_or_default();
+            }
+        }
+    }
+
+    fn read_devices(&self) {
+        let mut buffer = [0_u8; EVENT_SIZE * 64];
+
+        // Unplugged gamepads fail to read, that's when they are forgotten.
+        self.devices.borrow_mut().retain_mut(|device| loop {
+            match (&device.file).read(&mut buffer) {
+                Ok(length @ 1..) => {
+                    for event in parse_events(&buffer[..length]) {
+                        let id = device.id;
+                        device.state.translate(&event, |input| {
+                            self.push_event(id, Event::Input(input));
+                        });
+                    }
+                }
+
+                Err(error) if error.kind() == ErrorKind::WouldBlock => return true,
+                Err(error) if error.kind() == ErrorKind::Interrupted => {}
+
+                _ => {
+                    self.push_event(device.id, Event::GamepadDisconnected);
+                    return false;
+                }
+            }
+        });
+    }
+
+    fn open(&self, path: &Path) {
+        let known = self
+            .devices
+            .borrow()
+            .iter()
+            .any(|device| device.path == path);
+
+        // Changes to the permissions of an open device are reported too.
+        if known {
+            return;
+        }
+
+        let Ok(file) = OpenOptions::new()
+            .read(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open(path)
+        else {
+            return;
+        };
+
+        let Some(state) = (unsafe { State::query(file.as_raw_fd()) }) else {
+            return;
+        };
+
+        let id = GamepadId(self.next_id.get());
+        self.next_id.set(id.0 + 1);
+
+        self.devices.borrow_mut().push(Device {
+            id,
+            path: path.to_owned(),
+            file,
+            state,
+        });
+
+        self.push_event(id, Event::GamepadConnected);
+    }
+
+    fn push_event(&self, id: GamepadId, event: Event) {
+        self.pending.borrow_mut().push_back((id, event));
+    }
+}
+
+impl State {
+    /// Keyboards, mice and joysticks are evdev devices too, only gamepads are kept.
+    unsafe fn query(fd: c_int) -> Option<Self> {
+        let mut keys = [0_u8; libc::KEY_MAX as usize / 8 + 1];
+
+        if libc::ioctl(fd, eviocgbit(EV_KEY, keys.len()) as _, keys.as_mut_ptr()) < 0 {
+            return None;
+        }
+
+        if keys[BTN_SOUTH as usize / 8] & 1 << (BTN_SOUTH % 8) == 0 {
+            return None;
+        }
+
+        let axes = array::from_fn(|code| {
+            translate_axis(code as _)?;
+
+            let mut info = mem::zeroed::<libc::input_absinfo>();
+            let found = libc::ioctl(fd, eviocgabs(code as _) as _, &mut info) >= 0;
+
+            // Axes the device doesn't have are reported with an empty range.
+            (found && info.maximum > info.minimum).then_some(info)
+        });
+
+        Some(Self { axes, hat: [0; 2] })
+    }
+
+    fn translate(&mut self, event: &libc::input_event, mut emit: impl FnMut(Input)) {
+        let libc::input_event {
+            type_, code, value, ..
+        } = *event;
+
+        match (type_, code) {
+            // Gamepads don't repeat held buttons, but the value would be 2 if they did.
+            (EV_KEY, _) if value != 2 => {
+                if let Some(button) = translate_button(code) {
+                    let pressed = value != 0;
+                    emit(Input::GamepadButton { button, pressed });
+                }
+            }
+
+            // Plenty of gamepads report the D-pad as a hat, which is turned back into buttons.
+            (EV_ABS, ABS_HAT0X | ABS_HAT0Y) => {
+                let index = (code - ABS_HAT0X) as usize;
+                let previous = mem::replace(&mut self.hat[index], value.signum());
+
+                let buttons = match code {
+                    ABS_HAT0X => [GamepadButton::DPadLeft, GamepadButton::DPadRight],
+                    _ => [GamepadButton::DPadUp, GamepadButton::DPadDown],
+                };
+
+                for (direction, button) in [-1, 1].into_iter().zip(buttons) {
+                    let (was_pressed, pressed) =
+                        (previous == direction, value.signum() == direction);
+
+                    if was_pressed != pressed {
+                        emit(Input::GamepadButton { button, pressed });
+                    }
+                }
+            }
+
+            (EV_ABS, _) => {
+                let info = self.axes.get(code as usize).copied().flatten();
+
+                if let (Some(axis), Some(info)) = (translate_axis(code), info) {
+                    let value = normalize(axis, &info, value);
+                    emit(Input::GamepadAxis { axis, value });
+                }
+            }
+
+            // Dropped events are not resynchronized, every control catches up on its next change.
+            _ => {}
+        }
+    }
+}
+
+/// Split a stream of raw events, as read from a device (or a recording of one).
+fn parse_events(bytes: &[u8]) -> impl Iterator<Item = libc::input_event> + '_ {
+    bytes
+        .chunks_exact(EVENT_SIZE)
+        .map(|chunk| unsafe { ptr::read_unaligned(chunk.as_ptr() as *const libc::input_event) })
+}
+
+fn normalize(axis: GamepadAxis, info: &libc::input_absinfo, value: i32) -> f32 {
+    let range = info.maximum as f32 - info.minimum as f32;
+    let position = ((value as f32 - info.minimum as f32) / range).clamp(0.0, 1.0);
+
+    if matches!(axis, GamepadAxis::LeftTrigger | GamepadAxis::RightTrigger) {
+        return position;
+    }
+
+    // Sticks never rest exactly at the center, the driver tells how far off they may be.
+    let position = position * 2.0 - 1.0;
+    let flat = info.flat as f32 / range * 2.0;
+
+    match position.abs() <= flat {
+        true => 0.0,
+        false => position,
+    }
+}
+
+unsafe fn watch_devices() -> Option<File> {
+    let fd = libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC);
+
+    if fd < 0 {
+        return None;
+    }
+
+    let watch = File::from_raw_fd(fd);
+
+    // Devices are usually made readable after being created, which is reported as a change.
+    let mask = libc::IN_CREATE | libc::IN_ATTRIB;
+
+    (libc::inotify_add_watch(fd, c"/dev/input".as_ptr(), mask) >= 0).then_some(watch)
+}
+
+// Only reading ioctls are needed, with the direction bits of most architectures.
+const fn ioc_read(number: u16, size: usize) -> c_ulong {
+    2 << 30 | (size as c_ulong) << 16 | (b'E' as c_ulong) << 8 | number as c_ulong
+}
+
+const fn eviocgbit(kind: u16, length: usize) -> c_ulong {
+    ioc_read(0x20 + kind, length)
+}
+
+const fn eviocgabs(axis: u16) -> c_ulong {
+    ioc_read(0x40 + axis, mem::size_of::<libc::input_absinfo>())
+}
+
+fn translate_button(code: u16) -> Option<GamepadButton> {
+    let button = match code {
+        BTN_SOUTH => GamepadButton::South,
+        BTN_EAST => GamepadButton::East,
+        BTN_NORTH => GamepadButton::North,
+        BTN_WEST => GamepadButton::West,
+        BTN_TL => GamepadButton::LeftBumper,
+        BTN_TR => GamepadButton::RightBumper,
+        BTN_TL2 => GamepadButton::LeftTrigger,
+        BTN_TR2 => GamepadButton::RightTrigger,
+        BTN_SELECT => GamepadButton::Select,
+        BTN_START => GamepadButton::Start,
+        BTN_MODE => GamepadButton::Mode,
+        BTN_THUMBL => GamepadButton::LeftStick,
+        BTN_THUMBR => GamepadButton::RightStick,
+        BTN_DPAD_UP => GamepadButton::DPadUp,
+        BTN_DPAD_DOWN => GamepadButton::DPadDown,
+        BTN_DPAD_LEFT => GamepadButton::DPadLeft,
+        BTN_DPAD_RIGHT => GamepadButton::DPadRight,
+
+        _ => return None,
+    };
+
+    Some(button)
+}
+
+fn translate_axis(code: u16) -> Option<GamepadAxis> {
+    let axis = match code {
+        ABS_X => GamepadAxis::LeftX,
+        ABS_Y => GamepadAxis::LeftY,
+        ABS_RX => GamepadAxis::RightX,
+        ABS_RY => GamepadAxis::RightY,
+        ABS_Z => GamepadAxis::LeftTrigger,
+        ABS_RZ => GamepadAxis::RightTrigger,
+
+        _ => return None,
+    };
+
+    Some(axis)
+}
+
+#[cfg(test)]
+mod tests {
+    use core::slice;
+
+    use super::*;
+
+    const EV_SYN: u16 = 0x00;
+
+    const STICK: [i32; 3] = [0, 255, 15];
+    const TRIGGER: [i32; 3] = [0, 255, 0];
+
+    /// The bytes a device would hand over for the given (type, code, value) triplets.
+    fn record(events: &[(u16, u16, i32)]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        for &(type_, code, value) in events {
+            let mut event = unsafe { mem::zeroed::<libc::input_event>() };
+            event.type_ = type_;
+            event.code = code;
+            event.value = value;
+
+            let raw = &event as *const _ as *const u8;
+            bytes.extend_from_slice(unsafe { slice::from_raw_parts(raw, EVENT_SIZE) });
+        }
+
+        bytes
+    }
+
+    fn absinfo([minimum, maximum, flat]: [i32; 3]) -> libc::input_absinfo {
+        let mut info = unsafe { mem::zeroed::<libc::input_absinfo>() };
+        info.minimum = minimum;
+        info.maximum = maximum;
+        info.flat = flat;
+        info
+    }
+
+    fn gamepad() -> State {
+        let mut axes = [None; libc::ABS_CNT];
+
+        for code in [ABS_X, ABS_Y, ABS_RX, ABS_RY] {
+            axes[code as usize] = Some(absinfo(STICK));
+        }
+
+        for code in [ABS_Z, ABS_RZ] {
+            axes[code as usize] = Some(absinfo(TRIGGER));
+        }
+
+        State { axes, hat: [0; 2] }
+    }
+
+    fn translate(state: &mut State, events: &[(u16, u16, i32)]) -> Vec<Input> {
+        let mut inputs = Vec::new();
+
+        for event in parse_events(&record(events)) {
+            state.translate(&event, |input| inputs.push(input));
+        }
+
+        inputs
+    }
+
+    fn button(button: GamepadButton, pressed: bool) -> Input {
+        Input::GamepadButton { button, pressed }
+    }
+
+    fn axis(axis: GamepadAxis, value: f32) -> Input {
+        Input::GamepadAxis { axis, value }
+    }
+
+    #[test]
+    fn parse_ignores_trailing_bytes() {
+        let mut bytes = record(&[(EV_KEY, BTN_SOUTH, 1), (EV_SYN, 0, 0)]);
+        bytes.extend_from_slice(&[0; 3]);
+
+        assert_eq!(parse_events(&bytes).count(), 2);
+    }
+
+    #[test]
+    fn buttons() {
+        let mut state = gamepad();
+        let inputs = translate(
+            &mut state,
+            &[
+                (EV_KEY, BTN_SOUTH, 1),
+                (EV_SYN, 0, 0),
+                (EV_KEY, BTN_SOUTH, 0),
+                (EV_SYN, 0, 0),
+                (EV_KEY, BTN_START, 1),
+            ],
+        );
+
+        assert!(
+            inputs
+                == [
+                    button(GamepadButton::South, true),
+                    button(GamepadButton::South, false),
+                    button(GamepadButton::Start, true),
+                ]
+        );
+    }
+
+    #[test]
+    fn repeated_buttons() {
+        let mut state = gamepad();
+        let inputs = translate(
+            &mut state,
+            &[(EV_KEY, BTN_SOUTH, 1), (EV_KEY, BTN_SOUTH, 2)],
+        );
+
+        assert!(inputs == [button(GamepadButton::South, true)]);
+    }
+
+    #[test]
+    fn unknown_buttons() {
+        let mut state = gamepad();
+        let inputs = translate(&mut state, &[(EV_KEY, 0x100, 1)]);
+
+        assert!(inputs.is_empty());
+    }
+
+    #[test]
+    fn hat() {
+        let mut state = gamepad();
+        let inputs = translate(
+            &mut state,
+            &[
+                (EV_ABS, ABS_HAT0X, -1),
+                (EV_ABS, ABS_HAT0X, 1),
+                (EV_ABS, ABS_HAT0Y, -1),
+                (EV_ABS, ABS_HAT0X, 0),
+                (EV_ABS, ABS_HAT0Y, 0),
+            ],
+        );
+
+        assert!(
+            inputs
+                == [
+                    button(GamepadButton::DPadLeft, true),
+                    button(GamepadButton::DPadLeft, false),
+                    button(GamepadButton::DPadRight, true),
+                    button(GamepadButton::DPadUp, true),
+                    button(GamepadButton::DPadRight, false),
+                    button(GamepadButton::DPadUp, false),
+                ]
+        );
+    }
+
+    #[test]
+    fn triggers() {
+        let mut state = gamepad();
+        let inputs = translate(
+            &mut state,
+            &[
+                (EV_ABS, ABS_Z, 0),
+                (EV_ABS, ABS_Z, 255),
+                (EV_ABS, ABS_RZ, 300),
+                (EV_ABS, ABS_RZ, -5),
+            ],
+        );
+
+        assert!(
+            inputs
+                == [
+                    axis(GamepadAxis::LeftTrigger, 0.0),
+                    axis(GamepadAxis::LeftTrigger, 1.0),
+                    axis(GamepadAxis::RightTrigger, 1.0),
+                    axis(GamepadAxis::RightTrigger, 0.0),
+                ]
+        );
+    }
+
+    #[test]
+    fn sticks() {
+        let mut state = gamepad();
+        let inputs = translate(&mut state, &[(EV_ABS, ABS_X, 0), (EV_ABS, ABS_RY, 255)]);
+
+        assert!(
+            inputs
+                == [
+                    axis(GamepadAxis::LeftX, -1.0),
+                    axis(GamepadAxis::RightY, 1.0)
+                ]
+        );
+    }
+
+    #[test]
+    fn missing_axes() {
+        let mut state = gamepad();
+        state.axes[ABS_X as usize] = None;
+
+        let inputs = translate(&mut state, &[(EV_ABS, ABS_X, 0)]);
+
+        assert!(inputs.is_empty());
+    }
+
+    #[test]
+    fn dead_zone() {
+        let info = absinfo(STICK);
+
+        for value in [113, 127, 128, 142] {
+            assert_eq!(normalize(GamepadAxis::LeftX, &info, value), 0.0);
+        }
+
+        assert!(normalize(GamepadAxis::LeftX, &info, 150) > 0.0);
+        assert!(normalize(GamepadAxis::LeftX, &info, 105) < 0.0);
+    }
+
+    #[test]
+    fn dead_zone_spares_triggers() {
+        let info = absinfo([0, 255, 15]);
+
+        assert!(normalize(GamepadAxis::LeftTrigger, &info, 5) > 0.0);
+    }
+}
@@ -0,0 +1,16 @@
+#[cfg(target_os = "linux")]
+mod evdev;
+
+#[cfg(target_os = "linux")]
+pub use evdev::*;
+
+#[cfg(not(target_os = "linux"))]
+mod unsupported;
+
+#[cfg(not(target_os = "linux"))]
+pub use unsupported::*;
+
+/// Tags events with the gamepad they come from. Identifiers are never reused, not even when the
+/// same gamepad is plugged back in.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct GamepadId(u32);
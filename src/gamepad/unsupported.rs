@@ -0,0 +1,17 @@
+use core::iter;
+
+use super::GamepadId;
+use crate::event::Event;
+
+/// No gamepads are ever found on this platform.
+pub struct Gamepads;
+
+impl Gamepads {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn poll_events(&self) -> impl Iterator<Item = (GamepadId, Event)> + '_ {
+        iter::empty()
+    }
+}
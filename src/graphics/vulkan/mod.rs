@@ -27,25 +27,32 @@ const DEVICE_EXTENSIONS: [&CStr; 2] = [
     vk::KhrSwapchainFn::NAME,
 ];
 
-pub struct Graphics<'w> {
-    recreate_swapchain: Cell<bool>,
-    current_frame: usize,
-
-    window: &'w Window,
+/// The device, shared by the surfaces of every window.
+pub struct Graphics {
     instance: wrap::Instance,
-    surface: vk::SurfaceKHR,
     physical_device: wrap::PhysicalDevice,
     queue_family: u32,
     device: wrap::Device,
     queue: vk::Queue,
     command_pool: vk::CommandPool,
+}
+
+pub struct Surface<'g> {
+    recreate_swapchain: Cell<bool>,
+    current_frame: usize,
+
+    gfx: &'g Graphics,
+    window: &'g Window,
+    inner: vk::SurfaceKHR,
     swapchain: wrap::Swapchain,
 
     fifs: [(vk::CommandBuffer, vk::Fence, vk::Semaphore); 2],
 }
 
-impl<'win> Graphics<'win> {
-    pub fn new(window: &'win Window) -> Self {
+impl Graphics {
+    /// The window is only used to find a device able to present to it, other windows are assumed
+    /// to live on the same screen.
+    pub fn new(window: &Window) -> Self {
         // jmi2k: TODO: enable features.
         // jmi2k: TODO: test features.
         // jmi2k: mark command pool as TRANSIENT?
@@ -65,15 +72,7 @@ impl<'win> Graphics<'win> {
         let instance = unsafe { wrap::Instance::new(ash::Entry::linked(), &instance_info) }
             .expect("Failed to create instance");
 
-        #[cfg(windows)]
-        #[rustfmt::skip]
-        let surface = unsafe { instance.create_win32_surface(window) }
-            .expect("Failed to create surface");
-
-        #[cfg(unix)]
-        #[rustfmt::skip]
-        let surface = unsafe { instance.create_xlib_surface(window) }
-            .expect("Failed to create surface");
+        let surface = create_surface(&instance, window);
 
         let mut physical_devices = unsafe { wrap::PhysicalDevice::enumerate(&instance) }
             .expect("Failed to enumerate physical devices")
@@ -82,6 +81,7 @@ impl<'win> Graphics<'win> {
             .collect::<Vec<_>>();
 
         physical_devices.sort_by_key(|(device, _)| rank_physical_device(device));
+        unsafe { instance.destroy_surface(surface) };
 
         let (physical_device, queue_family) = physical_devices
             .into_iter()
@@ -114,8 +114,33 @@ impl<'win> Graphics<'win> {
         let command_pool = unsafe { device.create_command_pool(&commands_info, None) }
             .expect("Failed to create command pool");
 
+        Self {
+            instance,
+            physical_device,
+            queue_family,
+            device,
+            queue,
+            command_pool,
+        }
+    }
+
+    pub fn create_surface<'g>(&'g self, window: &'g Window) -> Surface<'g> {
+        let Self {
+            instance,
+            physical_device,
+            queue_family,
+            device,
+            command_pool,
+            ..
+        } = self;
+
+        let inner = create_surface(instance, window);
+        let supported = unsafe { physical_device.supports_surface(instance, *queue_family, inner) };
+
+        assert!(supported, "Device can't present to the window");
+
         let commands_info = vk::CommandBufferAllocateInfo::default()
-            .command_pool(command_pool)
+            .command_pool(*command_pool)
             .command_buffer_count(2)
             .level(vk::CommandBufferLevel::PRIMARY);
 
@@ -134,37 +159,45 @@ impl<'win> Graphics<'win> {
             (command_buffers[idx], available, acquired)
         });
 
-        Self {
+        Surface {
             recreate_swapchain: true.into(),
             current_frame: 0,
 
+            gfx: self,
             window,
-            instance,
-            surface,
-            physical_device,
-            queue_family,
-            device,
-            queue,
-            command_pool,
+            inner,
             swapchain: Default::default(),
             fifs,
         }
     }
+}
 
+impl Drop for Graphics {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.destroy_command_pool(self.command_pool, None);
+            self.device.destroy_device(None);
+            self.instance.destroy_instance(None);
+        }
+    }
+}
+
+impl Surface<'_> {
     pub fn invalidate_swapchain(&self) {
         self.recreate_swapchain.set(true);
     }
 
     pub fn prepare_frame(&mut self, mut callback: impl FnMut(Frame)) {
-        if self.recreate_swapchain.get() {
-            unsafe { self.recreate_swapchain() };
+        if self.recreate_swapchain.get() && unsafe { self.recreate_swapchain() }.is_none() {
+            // A zero-sized surface (e.g. minimized window) can't have a swapchain, try again later.
+            return;
         }
 
+        let Graphics { device, queue, .. } = self.gfx;
         let (commands, available, acquired) = self.fifs[self.current_frame & 1];
-        unsafe { self.device.wait_for_fences(&[available], true, u64::MAX) }.unwrap();
+        unsafe { device.wait_for_fences(&[available], true, u64::MAX) }.unwrap();
 
-        #[rustfmt::skip]
-        let acquire_result = unsafe { self.device.acquire_image(&self.swapchain, acquired) };
+        let acquire_result = unsafe { device.acquire_image(&self.swapchain, acquired) };
 
         let (idx, bad) = match acquire_result {
             Ok(res) => res,
@@ -184,15 +217,15 @@ impl<'win> Graphics<'win> {
         let begin_info = vk::CommandBufferBeginInfo::default()
             .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
 
-        unsafe { self.device.reset_fences(&[available]) }.unwrap();
+        unsafe { device.reset_fences(&[available]) }.unwrap();
 
-        unsafe { self.device.begin_command_buffer(commands, &begin_info) }
+        unsafe { device.begin_command_buffer(commands, &begin_info) }
             .expect("Failed to begin recording command buffer");
 
         let (image, view, rendered) = self.swapchain.image(idx);
 
         callback(Frame {
-            device: &self.device,
+            device,
             image,
             view,
             extent: self.swapchain.extent(),
@@ -211,14 +244,14 @@ impl<'win> Graphics<'win> {
             .swapchains(slice::from_ref(&self.swapchain))
             .wait_semaphores(slice::from_ref(&rendered));
 
-        unsafe { self.device.end_command_buffer(commands) }
+        unsafe { device.end_command_buffer(commands) }
             .expect("Failed to finish recording command buffer");
 
         #[rustfmt::skip]
-        unsafe { self.device.queue_submit(self.queue, &[submit_info], available) }
+        unsafe { device.queue_submit(*queue, &[submit_info], available) }
             .expect("Failed to submit commands to queue");
 
-        match unsafe { self.device.present(self.queue, &present_info) } {
+        match unsafe { device.present(*queue, &present_info) } {
             Ok(_) | Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => {}
             _ => panic!("Failed to present image"),
         }
@@ -226,12 +259,19 @@ impl<'win> Graphics<'win> {
         self.current_frame += 1;
     }
 
-    unsafe fn recreate_swapchain(&mut self) -> &wrap::Swapchain {
+    unsafe fn recreate_swapchain(&mut self) -> Option<&wrap::Swapchain> {
         // jmi2k: TODO: allow selecting present mode.
 
-        let present_mode = self
-            .instance
-            .surface_present_modes(&self.physical_device, &self.surface)
+        let Graphics {
+            instance,
+            physical_device,
+            queue_family,
+            device,
+            ..
+        } = self.gfx;
+
+        let present_mode = instance
+            .surface_present_modes(physical_device, &self.inner)
             .expect("Failed to get present modes")
             .into_iter()
             .find(|mode| *mode == vk::PresentModeKHR::IMMEDIATE)
@@ -241,18 +281,17 @@ impl<'win> Graphics<'win> {
             min_image_count,
             mut max_image_count,
             current_extent,
+            supported_composite_alpha,
             ..
-        } = self
-            .instance
-            .surface_capabilities(&self.physical_device, &self.surface)
+        } = instance
+            .surface_capabilities(physical_device, &self.inner)
             .expect("Failed to get surface capabilities");
 
         let vk::SurfaceFormatKHR {
             format,
             color_space,
-        } = self
-            .instance
-            .surface_formats(&self.physical_device, &self.surface)
+        } = instance
+            .surface_formats(physical_device, &self.inner)
             .expect("Failed to get surface formats")
             .into_iter()
             .min_by_key(rank_surface_format)
@@ -262,6 +301,26 @@ impl<'win> Graphics<'win> {
             max_image_count = u32::MAX;
         }
 
+        // Drawing is opaque over a transparent clear, either kind of blending gets that right.
+        let composite_alphas = match self.window.is_transparent() {
+            true => &[
+                vk::CompositeAlphaFlagsKHR::PRE_MULTIPLIED,
+                vk::CompositeAlphaFlagsKHR::POST_MULTIPLIED,
+                vk::CompositeAlphaFlagsKHR::INHERIT,
+            ][..],
+
+            false => &[
+                vk::CompositeAlphaFlagsKHR::OPAQUE,
+                vk::CompositeAlphaFlagsKHR::INHERIT,
+            ][..],
+        };
+
+        let composite_alpha = composite_alphas
+            .iter()
+            .copied()
+            .find(|&alpha| supported_composite_alpha.contains(alpha))
+            .unwrap_or(vk::CompositeAlphaFlagsKHR::OPAQUE);
+
         let vk::Extent2D {
             mut width,
             mut height,
@@ -271,37 +330,53 @@ impl<'win> Graphics<'win> {
             [width, height] = self.window.inner_size();
         }
 
+        if width == 0 || height == 0 {
+            return None;
+        }
+
         let swapchain_info = vk::SwapchainCreateInfoKHR::default()
-            .surface(self.surface)
+            .surface(self.inner)
             .image_format(format)
             .image_color_space(color_space)
             .image_extent(vk::Extent2D { width, height })
             .min_image_count(u32::clamp(3, min_image_count, max_image_count))
             .present_mode(present_mode)
-            .queue_family_indices(slice::from_ref(&self.queue_family));
+            .composite_alpha(composite_alpha)
+            .queue_family_indices(slice::from_ref(queue_family));
 
         let new_swapchain = self
             .swapchain
-            .recreate(&self.device, swapchain_info)
+            .recreate(device, swapchain_info)
             .expect("Failed to recreate swapchain");
 
         self.recreate_swapchain.set(false);
         self.swapchain = new_swapchain;
 
-        &self.swapchain
+        Some(&self.swapchain)
     }
 }
 
-impl Drop for Graphics<'_> {
+impl Drop for Surface<'_> {
     fn drop(&mut self) {
+        let Graphics {
+            instance,
+            device,
+            command_pool,
+            ..
+        } = self.gfx;
+
         unsafe {
-            _ = self.device.device_wait_idle();
+            _ = device.device_wait_idle();
 
-            self.swapchain.teardown(&self.device);
-            self.device.destroy_command_pool(self.command_pool, None);
-            self.device.destroy_device(None);
-            self.instance.destroy_surface(self.surface);
-            self.instance.destroy_instance(None);
+            self.swapchain.teardown(device);
+
+            for (commands, available, acquired) in self.fifs {
+                device.destroy_fence(available, None);
+                device.destroy_semaphore(acquired, None);
+                device.free_command_buffers(*command_pool, &[commands]);
+            }
+
+            instance.destroy_surface(self.inner);
         }
     }
 }
@@ -315,6 +390,20 @@ pub struct Frame<'g> {
     commands: vk::CommandBuffer,
 }
 
+fn create_surface(instance: &wrap::Instance, window: &Window) -> vk::SurfaceKHR {
+    #[cfg(windows)]
+    #[rustfmt::skip]
+    let surface = unsafe { instance.create_win32_surface(window) }
+        .expect("Failed to create surface");
+
+    #[cfg(unix)]
+    #[rustfmt::skip]
+    let surface = unsafe { instance.create_xlib_surface(window) }
+        .expect("Failed to create surface");
+
+    surface
+}
+
 pub fn is_srgb(format: vk::Format) -> bool {
     #[rustfmt::skip]
     matches!(format,
@@ -120,6 +120,7 @@ impl Renderer {
             .image_layout(vk::ImageLayout::ATTACHMENT_OPTIMAL)
             .load_op(vk::AttachmentLoadOp::CLEAR)
             .store_op(vk::AttachmentStoreOp::STORE)
+            // Transparent black, windows with alpha show what is behind where nothing is drawn.
             .clear_value(Default::default());
 
         let rendering_info = vk::RenderingInfo::default()
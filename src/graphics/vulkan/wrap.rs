@@ -185,7 +185,6 @@ impl Swapchain {
     ) -> VkResult<Self> {
         swapchain_info = swapchain_info
             .clipped(true)
-            .composite_alpha(vk::CompositeAlphaFlagsKHR::OPAQUE)
             .image_array_layers(1)
             .image_sharing_mode(vk::SharingMode::EXCLUSIVE)
             .image_usage(vk::ImageUsageFlags::COLOR_ATTACHMENT)
@@ -3,13 +3,15 @@
 #![feature(variant_count)]
 
 use core::time::Duration;
-use std::time::Instant;
+use std::{ffi::CString, time::Instant};
 
-use event::{Action, EventHandler, Input};
+use event::{Action, Event, EventHandler, Input, Key};
+use gamepad::Gamepads;
 use graphics::{render::Renderer, Graphics};
-use window::Window;
+use window::{EventLoop, RunMode};
 
 mod event;
+mod gamepad;
 mod graphics;
 mod utils;
 mod window;
@@ -17,49 +19,152 @@ mod window;
 const TICK_DURATION: Duration = Duration::from_micros(1_000_000 / 32);
 const TOO_MUCH_TIME: Duration = Duration::from_micros(8_000_000 / 32);
 
+// Freeze the simulation and only redraw every now and then while the window is in the background.
+const PAUSE_UNFOCUSED: bool = false;
+const UNFOCUSED_FRAME_DURATION: Duration = Duration::from_millis(250);
+
+const FPS_READOUT_INTERVAL: Duration = Duration::from_secs(1);
+
 #[rustfmt::skip]
-const BINDINGS: [(Input, Action); 1] = [
+const BINDINGS: [(Input, Action); 3] = [
     (Input::Close, Action::Exit),
+    (key_press(Key::Escape), Action::Exit),
+    (key_press(Key::F11), Action::ToggleFullscreen),
 ];
 
 fn main() {
     #[rustfmt::skip]
-    let window = Window::new(c"ash-sandbox", 1_024, 512)
+    let event_loop = EventLoop::new()
+        .expect("Failed to create event loop");
+
+    #[rustfmt::skip]
+    let window = event_loop.create_window(c"ash-sandbox", 1_024, 512)
         .expect("Failed to create window");
 
     let event_handler = EventHandler::from_iter(BINDINGS);
-    let mut gfx = Graphics::new(&window);
+    let gamepads = Gamepads::new();
+    let gfx = Graphics::new(&window);
+    let mut surface = gfx.create_surface(&window);
     let renderer = Renderer::new(&gfx);
     let mut tick = false;
+    let mut visible = true;
+    let mut paused = false;
 
     let mut then = Instant::now();
     let mut accrued_time = Duration::ZERO;
 
-    window.run(|event| {
-        match event_handler.handle(event) {
-            Action::Exit => return,
-            Action::Idle => tick = true,
-            _ => {}
-        }
+    let mut frames = 0;
+    let mut last_readout = then;
+
+    event_loop
+        .run(|_, event| {
+            // Gamepads don't wake the loop up, they are polled whenever it goes idle.
+            let gamepad_events = match event {
+                Event::Idle => gamepads.poll_events().map(|(_, event)| event).collect(),
+                _ => Vec::new(),
+            };
+
+            for event in gamepad_events.into_iter().chain([event]) {
+                match event_handler.handle(event) {
+                    Action::Exit => return event_loop.exit(),
+                    Action::Idle => tick = true,
+
+                    Action::Resize(_) => {
+                        // Some drivers never report the swapchain going out of date, don't wait.
+                        surface.invalidate_swapchain();
+                        tick = true;
+                    }
+
+                    Action::ToggleFullscreen => window.set_fullscreen(!window.is_fullscreen()),
+
+                    Action::Suspend => {
+                        visible = false;
+                        event_loop.set_run_mode(run_mode(visible, paused));
+                    }
+
+                    Action::Resume => {
+                        visible = true;
+                        event_loop.set_run_mode(run_mode(visible, paused));
+                        surface.invalidate_swapchain();
+                    }
+
+                    Action::Focus => {
+                        paused = false;
+                        event_loop.set_run_mode(run_mode(visible, paused));
+                    }
+
+                    Action::Unfocus if PAUSE_UNFOCUSED => {
+                        paused = true;
+                        event_loop.set_run_mode(run_mode(visible, paused));
+                    }
 
-        if !tick {
-            return;
-        }
+                    _ => {}
+                }
+            }
 
-        tick = false;
+            if !tick {
+                return;
+            }
 
-        let now = Instant::now();
-        accrued_time += now - then;
-        then = now;
+            tick = false;
 
-        while accrued_time >= TOO_MUCH_TIME {
-            accrued_time -= TICK_DURATION;
-        }
+            let now = Instant::now();
+            accrued_time += now - then;
+            then = now;
+
+            // Time doesn't flow for a paused simulation, it resumes where it was left.
+            if paused {
+                accrued_time = Duration::ZERO;
+            }
+
+            while accrued_time >= TOO_MUCH_TIME {
+                accrued_time -= TICK_DURATION;
+            }
+
+            while accrued_time >= TICK_DURATION {
+                accrued_time -= TICK_DURATION;
+            }
+
+            if !visible {
+                return;
+            }
+
+            surface.prepare_frame(|frame| renderer.render(frame));
+            frames += 1;
+
+            let elapsed = now - last_readout;
+
+            if elapsed < FPS_READOUT_INTERVAL {
+                return;
+            }
+
+            let fps = frames as f64 / elapsed.as_secs_f64();
+            let title = CString::new(format!("ash-sandbox ({fps:.0} fps)")).unwrap();
+
+            window.set_title(&title);
+            frames = 0;
+            last_readout = now;
+        })
+        .expect("Event loop failed");
+}
+
+/// Shortcuts follow the labels of the active layout, and don't fire again while held.
+const fn key_press(symbol: Key) -> Input {
+    Input::Key {
+        symbol: Some(symbol),
+        position: None,
+        pressed: true,
+        repeat: false,
+    }
+}
 
-        while accrued_time >= TICK_DURATION {
-            accrued_time -= TICK_DURATION;
-        }
+fn run_mode(visible: bool, paused: bool) -> RunMode {
+    match (visible, paused) {
+        (true, false) => RunMode::Poll,
+        (true, true) => RunMode::Wait(Some(UNFOCUSED_FRAME_DURATION)),
 
-        gfx.prepare_frame(|frame| renderer.render(frame));
-    });
+        // Keep the simulation going, but without spinning while there is nothing to show.
+        (false, false) => RunMode::Wait(Some(TICK_DURATION)),
+        (false, true) => RunMode::Wait(None),
+    }
 }
@@ -1,3 +1,8 @@
+use core::{cell::Cell, time::Duration};
+use std::time::Instant;
+
+use crate::event::Event;
+
 #[cfg(windows)]
 mod win32;
 
@@ -9,3 +14,125 @@ mod x11;
 
 #[cfg(unix)]
 pub use x11::*;
+
+#[derive(Copy, Clone)]
+pub enum RunMode {
+    /// Deliver `Event::Idle` whenever the event queue is empty.
+    Poll,
+
+    /// Sleep until events arrive, delivering `Event::Idle` only when the timeout expires or a
+    /// redraw is requested.
+    Wait(Option<Duration>),
+}
+
+/// What every backend keeps track of to drive `run`.
+struct LoopState {
+    run_mode: Cell<RunMode>,
+    redraw_requested: Cell<bool>,
+    exit_requested: Cell<bool>,
+}
+
+impl LoopState {
+    fn new() -> Self {
+        Self {
+            run_mode: RunMode::Poll.into(),
+            redraw_requested: false.into(),
+            exit_requested: false.into(),
+        }
+    }
+
+    /// Deliver the events the backend polls until exit is requested, going idle as the run mode
+    /// says and waiting for more in between.
+    fn run<I, E>(
+        &self,
+        mut poll_events: impl FnMut() -> I,
+        mut wait_for_events: impl FnMut(Option<Duration>),
+        mut take_error: impl FnMut() -> Option<E>,
+        mut cb: impl FnMut(Option<WindowId>, Event),
+    ) -> Result<(), E>
+    where
+        I: Iterator<Item = (WindowId, Event)>,
+    {
+        let mut last_idle = Instant::now();
+
+        self.exit_requested.set(false);
+
+        loop {
+            for (window, event) in poll_events() {
+                cb(Some(window), event);
+
+                if self.exit_requested.get() {
+                    return Ok(());
+                }
+            }
+
+            if let Some(error) = take_error() {
+                return Err(error);
+            }
+
+            let now = Instant::now();
+
+            // The mode may change from inside the callback, so the deadline is computed every time.
+            let deadline = match self.run_mode.get() {
+                RunMode::Poll => Some(last_idle),
+                RunMode::Wait(timeout) => timeout.map(|timeout| last_idle + timeout),
+            };
+
+            let redraw_requested = self.redraw_requested.take();
+
+            if redraw_requested || deadline.is_some_and(|deadline| now >= deadline) {
+                last_idle = now;
+                cb(None, Event::Idle);
+
+                if self.exit_requested.get() {
+                    return Ok(());
+                }
+
+                continue;
+            }
+
+            wait_for_events(deadline.map(|deadline| deadline - now));
+        }
+    }
+}
+
+/// Round up, waking up early would only lead to another wait.
+fn timeout_millis(timeout: Duration) -> u128 {
+    timeout.as_micros().div_ceil(1_000)
+}
+
+#[allow(unused)]
+#[derive(Copy, Clone, Default, Eq, PartialEq)]
+pub enum CursorIcon {
+    #[default]
+    Arrow,
+    Pointer,
+    Text,
+    Crosshair,
+    Move,
+    Wait,
+    Progress,
+    NotAllowed,
+    Help,
+    ResizeHorizontal,
+    ResizeVertical,
+    ResizeDiagonal,
+    ResizeAntiDiagonal,
+}
+
+#[allow(unused)]
+#[derive(Clone)]
+pub struct Monitor {
+    pub name: String,
+    pub position: [i32; 2],
+    pub size: [u32; 2],
+
+    /// Millimeters, zero when unknown (e.g. projectors).
+    pub physical_size: [u32; 2],
+
+    /// Hertz, unknown if the mode timings make no sense.
+    pub refresh_rate: Option<f64>,
+
+    pub scale_factor: f64,
+    pub primary: bool,
+}
@@ -1,50 +1,68 @@
 use core::{
-    cell::Cell,
+    cell::{Cell, RefCell},
     ffi::CStr,
-    ptr,
-    sync::atomic::{AtomicBool, Ordering},
+    iter,
+    time::Duration,
 };
+use std::{collections::VecDeque, rc::Rc};
 
 use windows::{
     core::{s, Result, PCSTR},
     Win32::{
-        Foundation::{HINSTANCE, HWND, LPARAM, LRESULT, WPARAM},
+        Foundation::{HINSTANCE, HWND, LPARAM, LRESULT, RECT, WPARAM},
         System::LibraryLoader::GetModuleHandleA,
         UI::WindowsAndMessaging::{
             CreateWindowExA, DefWindowProcA, DestroyWindow, DispatchMessageA, GetClientRect,
-            GetWindowLongPtrA, LoadCursorA, PeekMessageA, PostQuitMessage, RegisterClassA,
-            SetWindowLongPtrA, CS_HREDRAW, CS_OWNDC, CS_VREDRAW, CW_USEDEFAULT, GWLP_USERDATA,
-            IDC_ARROW, MSG, PM_REMOVE, WINDOW_EX_STYLE, WM_CLOSE, WM_DESTROY, WM_QUIT, WNDCLASSA,
-            WS_OVERLAPPEDWINDOW, WS_VISIBLE,
+            GetSystemMetrics, GetWindowLongPtrA, GetWindowRect, LoadCursorA,
+            MsgWaitForMultipleObjects, PeekMessageA, RegisterClassA, SetWindowLongPtrA,
+            SetWindowPos, SetWindowTextA, CS_HREDRAW, CS_OWNDC, CS_VREDRAW, CW_USEDEFAULT,
+            GWLP_USERDATA, GWL_STYLE, HWND_TOP, IDC_ARROW, MSG, PM_REMOVE, QS_ALLINPUT,
+            SM_CXSCREEN, SM_CYSCREEN, SWP_FRAMECHANGED, WINDOW_EX_STYLE, WM_CLOSE, WM_KILLFOCUS,
+            WM_SETFOCUS, WNDCLASSA, WS_OVERLAPPEDWINDOW, WS_POPUP, WS_VISIBLE,
         },
     },
 };
 
+use super::{timeout_millis, LoopState, RunMode};
 use crate::{
     event::{Event, Input},
     utils,
 };
 
+const CLASS_NAME: PCSTR = s!("window");
+
+/// Owns the state every window created from it shares.
+pub struct EventLoop {
+    shared: Rc<Shared>,
+}
+
+struct Shared {
+    instance: HINSTANCE,
+    state: LoopState,
+    pending: RefCell<VecDeque<(WindowId, Event)>>,
+}
+
+/// Tags events with the window they belong to.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct WindowId(isize);
+
 pub struct Window {
     inner: HWND,
-    instance: HINSTANCE,
-    callback: Cell<*const *mut dyn FnMut(Event)>,
-    used: AtomicBool,
+    shared: Rc<Shared>,
+    fullscreen: Cell<bool>,
+    windowed_rect: Cell<RECT>,
 }
 
 utils::wrap! { Window, HWND }
 
-impl Window {
-    pub fn new(title: &CStr, width: u32, height: u32) -> Result<Self> {
-        // jmi2k: TODO: null & error checks everywhere!
-        // jmi2k: TODO: use AdjustWindowRectEx to calculate outer size from inner_size.
-
+impl EventLoop {
+    pub fn new() -> Result<Self> {
         let instance = unsafe { GetModuleHandleA(None) }?.into();
 
         let class = WNDCLASSA {
             hCursor: unsafe { LoadCursorA(None, PCSTR(IDC_ARROW.0 as _)) }?,
             hInstance: instance,
-            lpszClassName: s!("window"),
+            lpszClassName: CLASS_NAME,
             style: CS_HREDRAW | CS_VREDRAW | CS_OWNDC,
             lpfnWndProc: Some(handle_event),
             ..Default::default()
@@ -52,10 +70,25 @@ impl Window {
 
         unsafe { RegisterClassA(&class) };
 
+        let shared = Shared {
+            instance,
+            state: LoopState::new(),
+            pending: Default::default(),
+        };
+
+        Ok(Self {
+            shared: shared.into(),
+        })
+    }
+
+    pub fn create_window(&self, title: &CStr, width: u32, height: u32) -> Result<Rc<Window>> {
+        // jmi2k: TODO: null & error checks everywhere!
+        // jmi2k: TODO: use AdjustWindowRectEx to calculate outer size from inner_size.
+
         let inner = unsafe {
             CreateWindowExA(
                 WINDOW_EX_STYLE::default(),
-                class.lpszClassName,
+                CLASS_NAME,
                 PCSTR::from_raw(title.as_ptr() as _),
                 WS_OVERLAPPEDWINDOW | WS_VISIBLE,
                 CW_USEDEFAULT,
@@ -64,21 +97,76 @@ impl Window {
                 height as _,
                 None,
                 None,
-                instance,
+                self.shared.instance,
                 None,
             )
         };
 
-        Ok(Self {
-            instance,
+        let window = Rc::new(Window {
             inner,
-            callback: Cell::new(ptr::null()),
-            used: false.into(),
+            shared: self.shared.clone(),
+            fullscreen: false.into(),
+            windowed_rect: Default::default(),
+        });
+
+        // The allocation behind an `Rc` never moves, the window procedure can keep pointing at it.
+        unsafe { SetWindowLongPtrA(inner, GWLP_USERDATA, Rc::as_ptr(&window) as _) };
+
+        Ok(window)
+    }
+
+    /// Deliver events until `exit` is called. Events are tagged with the window they belong to,
+    /// `Event::Idle` concerns all of them and has no window.
+    pub fn run(&self, cb: impl FnMut(Option<WindowId>, Event)) -> Result<()> {
+        self.shared
+            .state
+            .run(|| self.poll_events(), wait_for_events, || None, cb)
+    }
+
+    /// Drain the events that are ready without blocking. `Event::Idle` is never produced, the
+    /// caller owns the main loop and decides when to do its work.
+    pub fn poll_events(&self) -> impl Iterator<Item = (WindowId, Event)> + '_ {
+        iter::from_fn(|| loop {
+            if let Some(event) = self.shared.pending.borrow_mut().pop_front() {
+                return Some(event);
+            }
+
+            let mut message = MSG::default();
+
+            if !unsafe { PeekMessageA(&mut message, None, 0, 0, PM_REMOVE) }.as_bool() {
+                return None;
+            }
+
+            unsafe { DispatchMessageA(&message) };
         })
     }
 
+    /// Deliver the events that are ready, without blocking.
+    #[allow(unused)]
+    pub fn dispatch_pending(&self, mut cb: impl FnMut(WindowId, Event)) -> Result<()> {
+        self.poll_events()
+            .for_each(|(window, event)| cb(window, event));
+
+        Ok(())
+    }
+
+    /// Make `run` return once the current callback is done.
+    pub fn exit(&self) {
+        self.shared.state.exit_requested.set(true);
+    }
+
+    pub fn set_run_mode(&self, mode: RunMode) {
+        self.shared.state.run_mode.set(mode);
+    }
+}
+
+impl Window {
+    pub fn id(&self) -> WindowId {
+        WindowId(self.inner.0)
+    }
+
     pub fn instance(&self) -> HINSTANCE {
-        self.instance
+        self.shared.instance
     }
 
     pub fn inner_size(&self) -> [u32; 2] {
@@ -92,32 +180,93 @@ impl Window {
         [width as _, height as _]
     }
 
-    pub fn run(&self, mut cb: impl FnMut(Event)) {
-        if self.used.fetch_or(true, Ordering::AcqRel) {
+    pub fn request_redraw(&self) {
+        self.shared.state.redraw_requested.set(true);
+    }
+
+    /// Windows only blends windows through layered windows, which Vulkan can't present to.
+    pub fn is_transparent(&self) -> bool {
+        false
+    }
+
+    pub fn set_title(&self, title: &CStr) {
+        _ = unsafe { SetWindowTextA(**self, PCSTR::from_raw(title.as_ptr() as _)) };
+    }
+
+    pub fn is_fullscreen(&self) -> bool {
+        self.fullscreen.get()
+    }
+
+    pub fn set_fullscreen(&self, fullscreen: bool) {
+        // jmi2k: TODO: use the monitor the window is on, not just the primary one.
+
+        if self.fullscreen.replace(fullscreen) == fullscreen {
             return;
         }
 
-        let mut message = MSG::default();
-        let fat_pointer = &mut cb as *mut dyn FnMut(_);
+        let (style, rect) = match fullscreen {
+            true => {
+                let mut rect = RECT::default();
+                _ = unsafe { GetWindowRect(**self, &mut rect) };
+                self.windowed_rect.set(rect);
 
-        self.callback.set(&fat_pointer as *const _ as _);
-        unsafe { SetWindowLongPtrA(**self, GWLP_USERDATA, self as *const _ as _) };
+                let right = unsafe { GetSystemMetrics(SM_CXSCREEN) };
+                let bottom = unsafe { GetSystemMetrics(SM_CYSCREEN) };
+                let screen_rect = RECT {
+                    right,
+                    bottom,
+                    ..Default::default()
+                };
 
-        while message.message != WM_QUIT {
-            if !unsafe { PeekMessageA(&mut message, None, 0, 0, PM_REMOVE) }.as_bool() {
-                cb(Event::Idle);
-                continue;
+                (WS_POPUP, screen_rect)
             }
 
-            unsafe { DispatchMessageA(&message) };
+            false => (WS_OVERLAPPEDWINDOW, self.windowed_rect.get()),
+        };
+
+        let width = rect.right - rect.left;
+        let height = rect.bottom - rect.top;
+
+        unsafe {
+            SetWindowLongPtrA(**self, GWL_STYLE, (style | WS_VISIBLE).0 as _);
+            _ = SetWindowPos(
+                **self,
+                HWND_TOP,
+                rect.left,
+                rect.top,
+                width,
+                height,
+                SWP_FRAMECHANGED,
+            );
         }
     }
 
-    fn callback(&self) -> *const *mut dyn FnMut(Event) {
-        self.callback.get()
+    fn push_event(&self, event: Event) {
+        self.shared
+            .pending
+            .borrow_mut()
+            .push_back((self.id(), event));
     }
 }
 
+impl Drop for Window {
+    fn drop(&mut self) {
+        unsafe {
+            // Destroying the window still sends messages, which must not reach it anymore.
+            SetWindowLongPtrA(**self, GWLP_USERDATA, 0);
+            _ = DestroyWindow(**self);
+        }
+    }
+}
+
+fn wait_for_events(timeout: Option<Duration>) {
+    let timeout = timeout.map_or(u32::MAX, |timeout| {
+        timeout_millis(timeout).min(u32::MAX as _) as _
+    });
+
+    unsafe { MsgWaitForMultipleObjects(None, false, timeout, QS_ALLINPUT) };
+}
+
 unsafe extern "system" fn handle_event(
     handle: HWND,
     message: u32,
@@ -125,20 +274,20 @@ unsafe extern "system" fn handle_event(
     lparam: LPARAM,
 ) -> LRESULT {
     let window = GetWindowLongPtrA(handle, GWLP_USERDATA) as *const Window;
-    let callback = window.as_ref().map(Window::callback);
 
-    match (callback, message) {
-        (Some(cb), WM_CLOSE) => {
+    match (window.as_ref(), message) {
+        // The window goes away when it is dropped, not as soon as the user asks to close it.
+        (Some(window), WM_CLOSE) => {
             let event = Event::Input(Input::Close);
 
-            (**cb)(event);
-            _ = DestroyWindow(handle);
-
+            window.push_event(event);
             LRESULT::default()
         }
 
-        (_, WM_DESTROY) => {
-            PostQuitMessage(0);
+        (Some(window), WM_SETFOCUS | WM_KILLFOCUS) => {
+            let event = Event::Focus(message == WM_SETFOCUS);
+
+            window.push_event(event);
             LRESULT::default()
         }
 
@@ -0,0 +1,292 @@
+use core::{
+    ffi::{c_ulong, CStr},
+    mem, ptr,
+};
+use std::rc::Rc;
+
+use x11::xlib::{
+    self, XChangeProperty, XCreateColormap, XCreateWindow, XDefaultRootWindow, XDefaultScreen,
+    XDefaultVisual, XDestroyWindow, XFreeColormap, XMapWindow, XMatchVisualInfo, XSelectInput,
+    XSetWMNormalHints, XSetWMProtocols,
+};
+
+use super::{dnd, error, intern_atom, store_title, EventLoop, Window, WindowError};
+
+// `_MOTIF_WM_HINTS` is not standardized by EWMH, but every window manager understands it.
+const MWM_HINTS_DECORATIONS: c_ulong = 1 << 1;
+
+pub struct WindowBuilder<'t> {
+    title: &'t CStr,
+    size: [u32; 2],
+    position: Option<[i32; 2]>,
+    min_size: Option<[u32; 2]>,
+    max_size: Option<[u32; 2]>,
+    resizable: bool,
+    decorations: bool,
+    transparent: bool,
+}
+
+#[allow(unused)]
+impl<'t> WindowBuilder<'t> {
+    pub fn new(title: &'t CStr) -> Self {
+        Self {
+            title,
+            size: [640, 480],
+            position: None,
+            min_size: None,
+            max_size: None,
+            resizable: true,
+            decorations: true,
+            transparent: false,
+        }
+    }
+
+    pub fn size(mut self, width: u32, height: u32) -> Self {
+        self.size = [width, height];
+        self
+    }
+
+    pub fn position(mut self, x: i32, y: i32) -> Self {
+        self.position = Some([x, y]);
+        self
+    }
+
+    pub fn min_size(mut self, width: u32, height: u32) -> Self {
+        self.min_size = Some([width, height]);
+        self
+    }
+
+    pub fn max_size(mut self, width: u32, height: u32) -> Self {
+        self.max_size = Some([width, height]);
+        self
+    }
+
+    pub fn resizable(mut self, resizable: bool) -> Self {
+        self.resizable = resizable;
+        self
+    }
+
+    pub fn decorations(mut self, decorations: bool) -> Self {
+        self.decorations = decorations;
+        self
+    }
+
+    /// Ask for an alpha channel, the window stays opaque if the server has no visual with one.
+    pub fn transparent(mut self, transparent: bool) -> Self {
+        self.transparent = transparent;
+        self
+    }
+
+    pub fn build(self, event_loop: &EventLoop) -> Result<Rc<Window>, WindowError> {
+        let connection = &event_loop.connection;
+        let display = connection.display;
+
+        // Vulkan can only present to windows backed by a TrueColor or DirectColor visual.
+        let visual_class = unsafe { (*XDefaultVisual(display, XDefaultScreen(display))).class };
+
+        if !matches!(visual_class, xlib::TrueColor | xlib::DirectColor) {
+            return Err(WindowError::BadVisual);
+        }
+
+        let [x, y] = self.position.unwrap_or_default();
+        let [width, height] = self.size;
+        let root = unsafe { XDefaultRootWindow(display) };
+
+        let trap = unsafe { error::ErrorTrap::set(display) };
+
+        let argb_visual = match self.transparent {
+            true => unsafe { find_argb_visual(display) },
+            false => None,
+        };
+
+        let mut attributes = unsafe { mem::zeroed::<xlib::XSetWindowAttributes>() };
+        let mut value_mask = xlib::CWBackPixel;
+
+        // Everything is inherited from the root window, unless a visual with alpha is used.
+        let (depth, visual) = match argb_visual {
+            None => (xlib::CopyFromParent, ptr::null_mut()),
+
+            Some(info) => {
+                attributes.colormap =
+                    unsafe { XCreateColormap(display, root, info.visual, xlib::AllocNone) };
+
+                value_mask |= xlib::CWColormap | xlib::CWBorderPixel;
+                (info.depth, info.visual)
+            }
+        };
+
+        let inner = unsafe {
+            XCreateWindow(
+                display,
+                root,
+                x,
+                y,
+                width,
+                height,
+                0,
+                depth,
+                xlib::InputOutput as _,
+                visual,
+                value_mask,
+                &mut attributes,
+            )
+        };
+
+        let input_context = connection
+            .input_method
+            .as_ref()
+            .and_then(|input_method| unsafe { input_method.create_context(inner) });
+
+        let mut input_mask = xlib::KeyPressMask
+            | xlib::KeyReleaseMask
+            | xlib::ButtonPressMask
+            | xlib::ButtonReleaseMask
+            | xlib::PointerMotionMask
+            | xlib::StructureNotifyMask
+            | xlib::VisibilityChangeMask
+            | xlib::FocusChangeMask
+            | xlib::PropertyChangeMask
+            | xlib::ExposureMask;
+
+        if let Some(input_context) = &input_context {
+            input_mask |= unsafe { input_context.event_mask() };
+        }
+
+        unsafe {
+            let mut protocols = [connection.wm_delete_window];
+
+            XSetWMProtocols(display, inner, protocols.as_mut_ptr(), protocols.len() as _);
+            XSelectInput(display, inner, input_mask);
+            store_title(display, inner, self.title);
+            self.set_size_hints(display, inner);
+
+            // Let drag sources know that files can be dropped here.
+            XChangeProperty(
+                display,
+                inner,
+                intern_atom(display, c"XdndAware"),
+                xlib::XA_ATOM,
+                32,
+                xlib::PropModeReplace,
+                &dnd::VERSION as *const _ as _,
+                1,
+            );
+
+            if !self.decorations {
+                self.remove_decorations(display, inner);
+            }
+
+            XMapWindow(display, inner);
+        };
+
+        if let Some(error) = unsafe { trap.finish() } {
+            unsafe {
+                if let Some(input_context) = &input_context {
+                    input_context.destroy();
+                }
+
+                XDestroyWindow(display, inner);
+
+                if attributes.colormap != 0 {
+                    XFreeColormap(display, attributes.colormap);
+                }
+            }
+
+            return Err(error);
+        }
+
+        let window = Window {
+            inner,
+            display,
+            connection: connection.clone(),
+            size: self.size.into(),
+            position: Default::default(),
+            mapped: false.into(),
+            obscured: false.into(),
+            focused: false.into(),
+            transparent: argb_visual.is_some(),
+            colormap: attributes.colormap,
+            fullscreen: false.into(),
+            scale_factor: 1.0.into(),
+            cursor_grabbed: false.into(),
+            motion_remainder: Default::default(),
+            cursor_icon: Default::default(),
+            cursor_visible: true.into(),
+            input_context,
+            text_input: false.into(),
+            held_keys: [false; 256].into(),
+            selections: Default::default(),
+            drag: Default::default(),
+        };
+
+        // The window manager may still move the window, which is reported as a change later on.
+        window.position.set(window.query_position());
+
+        let scale_factor = window
+            .current_monitor()
+            .map_or(1.0, |monitor| monitor.scale_factor);
+        window.scale_factor.set(scale_factor);
+
+        // Events are routed to windows that are still alive, the connection doesn't own them.
+        let window = Rc::new(window);
+        connection.windows.borrow_mut().push(Rc::downgrade(&window));
+
+        Ok(window)
+    }
+
+    unsafe fn set_size_hints(&self, display: *mut xlib::Display, window: xlib::Window) {
+        let mut hints = mem::zeroed::<xlib::XSizeHints>();
+
+        // A window that can't be resized is just one whose size is clamped to the initial one.
+        let (min_size, max_size) = match self.resizable {
+            true => (self.min_size, self.max_size),
+            false => (Some(self.size), Some(self.size)),
+        };
+
+        if let Some([x, y]) = self.position {
+            hints.flags |= xlib::USPosition | xlib::PPosition;
+            hints.x = x;
+            hints.y = y;
+        }
+
+        if let Some([width, height]) = min_size {
+            hints.flags |= xlib::PMinSize;
+            hints.min_width = width as _;
+            hints.min_height = height as _;
+        }
+
+        if let Some([width, height]) = max_size {
+            hints.flags |= xlib::PMaxSize;
+            hints.max_width = width as _;
+            hints.max_height = height as _;
+        }
+
+        XSetWMNormalHints(display, window, &mut hints);
+    }
+
+    unsafe fn remove_decorations(&self, display: *mut xlib::Display, window: xlib::Window) {
+        let motif_hints = intern_atom(display, c"_MOTIF_WM_HINTS");
+
+        // flags, functions, decorations, input mode, status.
+        let hints: [c_ulong; 5] = [MWM_HINTS_DECORATIONS, 0, 0, 0, 0];
+
+        XChangeProperty(
+            display,
+            window,
+            motif_hints,
+            motif_hints,
+            32,
+            xlib::PropModeReplace,
+            hints.as_ptr() as _,
+            hints.len() as _,
+        );
+    }
+}
+
+unsafe fn find_argb_visual(display: *mut xlib::Display) -> Option<xlib::XVisualInfo> {
+    let mut info = mem::zeroed();
+    let screen = XDefaultScreen(display);
+    let found = XMatchVisualInfo(display, screen, 32, xlib::TrueColor, &mut info) != 0;
+
+    found.then_some(info)
+}
@@ -0,0 +1,153 @@
+use core::{
+    ffi::{c_long, CStr},
+    ptr, slice,
+};
+
+use x11::xlib::{self, XChangeProperty, XFree, XGetWindowProperty, XSendEvent};
+
+use super::intern_atom;
+
+#[derive(Copy, Clone)]
+pub enum Selection {
+    Clipboard,
+    Primary,
+}
+
+impl Selection {
+    pub fn name(self) -> &'static CStr {
+        match self {
+            Self::Clipboard => c"CLIPBOARD",
+            Self::Primary => c"PRIMARY",
+        }
+    }
+
+    pub unsafe fn from_atom(display: *mut xlib::Display, atom: xlib::Atom) -> Option<Self> {
+        [Self::Clipboard, Self::Primary]
+            .into_iter()
+            .find(|selection| intern_atom(display, selection.name()) == atom)
+    }
+}
+
+/// Answer another client asking for the contents of a selection, refusing if there are none. The
+/// requestor may be gone already, errors have to be ignored.
+pub unsafe fn serve(
+    display: *mut xlib::Display,
+    request: &xlib::XSelectionRequestEvent,
+    text: Option<&str>,
+) {
+    let targets = intern_atom(display, c"TARGETS");
+    let utf8_string = intern_atom(display, c"UTF8_STRING");
+    let xlib::XSelectionRequestEvent {
+        requestor, target, ..
+    } = *request;
+
+    // Obsolete clients don't name a property, the target is used for it instead.
+    let property = match request.property {
+        0 => target,
+        property => property,
+    };
+
+    let converted = match text {
+        None => false,
+
+        Some(_) if target == targets => {
+            let atoms = [targets, utf8_string, xlib::XA_STRING];
+
+            XChangeProperty(
+                display,
+                requestor,
+                property,
+                xlib::XA_ATOM,
+                32,
+                xlib::PropModeReplace,
+                atoms.as_ptr() as _,
+                atoms.len() as _,
+            );
+            true
+        }
+
+        Some(text) if target == utf8_string || target == xlib::XA_STRING => {
+            // `STRING` is Latin-1, anything outside of it can't be represented.
+            let bytes = match target {
+                xlib::XA_STRING => text
+                    .chars()
+                    .map(|char| u8::try_from(char).unwrap_or(b'?'))
+                    .collect(),
+                _ => text.as_bytes().to_vec(),
+            };
+
+            XChangeProperty(
+                display,
+                requestor,
+                property,
+                target,
+                8,
+                xlib::PropModeReplace,
+                bytes.as_ptr(),
+                bytes.len() as _,
+            );
+            true
+        }
+
+        Some(_) => false,
+    };
+
+    let notify = xlib::XSelectionEvent {
+        type_: xlib::SelectionNotify,
+        serial: 0,
+        send_event: 1,
+        display,
+        requestor,
+        selection: request.selection,
+        target,
+        property: if converted { property } else { 0 },
+        time: request.time,
+    };
+
+    XSendEvent(display, requestor, 0, 0, &mut notify.into());
+}
+
+/// Read and delete the property a selection was converted into.
+pub unsafe fn take_property(
+    display: *mut xlib::Display,
+    window: xlib::Window,
+    property: xlib::Atom,
+) -> Option<String> {
+    let incr = intern_atom(display, c"INCR");
+    let mut kind = 0;
+    let mut format = 0;
+    let mut length = 0;
+    let mut remaining = 0;
+    let mut data = ptr::null_mut();
+
+    XGetWindowProperty(
+        display,
+        window,
+        property,
+        0,
+        c_long::MAX / 4,
+        1,
+        xlib::AnyPropertyType as _,
+        &mut kind,
+        &mut format,
+        &mut length,
+        &mut remaining,
+        &mut data,
+    );
+
+    if data.is_null() {
+        return None;
+    }
+
+    let bytes = slice::from_raw_parts(data, length as _);
+
+    // Transfers in chunks are only used for huge selections, which are not worth supporting.
+    let text = match kind {
+        _ if kind == incr || format != 8 => None,
+        xlib::XA_STRING => Some(bytes.iter().map(|&byte| byte as char).collect()),
+        _ => Some(String::from_utf8_lossy(bytes).into_owned()),
+    };
+
+    XFree(data as _);
+    text
+}
@@ -0,0 +1,46 @@
+use core::ffi::{c_uint, CStr};
+
+use x11::{
+    xcursor::XcursorLibraryLoadCursor,
+    xlib::{self, XCreateFontCursor},
+};
+
+use crate::window::CursorIcon;
+
+// Shapes from the core cursor font, see `X11/cursorfont.h`.
+const XC_X_CURSOR: c_uint = 0;
+const XC_BOTTOM_LEFT_CORNER: c_uint = 12;
+const XC_BOTTOM_RIGHT_CORNER: c_uint = 14;
+const XC_CROSSHAIR: c_uint = 34;
+const XC_FLEUR: c_uint = 52;
+const XC_HAND2: c_uint = 60;
+const XC_LEFT_PTR: c_uint = 68;
+const XC_QUESTION_ARROW: c_uint = 92;
+const XC_SB_H_DOUBLE_ARROW: c_uint = 108;
+const XC_SB_V_DOUBLE_ARROW: c_uint = 116;
+const XC_WATCH: c_uint = 150;
+const XC_XTERM: c_uint = 152;
+
+pub unsafe fn load(display: *mut xlib::Display, icon: CursorIcon) -> xlib::Cursor {
+    let (name, shape): (&CStr, _) = match icon {
+        CursorIcon::Arrow => (c"default", XC_LEFT_PTR),
+        CursorIcon::Pointer => (c"pointer", XC_HAND2),
+        CursorIcon::Text => (c"text", XC_XTERM),
+        CursorIcon::Crosshair => (c"crosshair", XC_CROSSHAIR),
+        CursorIcon::Move => (c"move", XC_FLEUR),
+        CursorIcon::Wait => (c"wait", XC_WATCH),
+        CursorIcon::Progress => (c"progress", XC_WATCH),
+        CursorIcon::NotAllowed => (c"not-allowed", XC_X_CURSOR),
+        CursorIcon::Help => (c"help", XC_QUESTION_ARROW),
+        CursorIcon::ResizeHorizontal => (c"ew-resize", XC_SB_H_DOUBLE_ARROW),
+        CursorIcon::ResizeVertical => (c"ns-resize", XC_SB_V_DOUBLE_ARROW),
+        CursorIcon::ResizeDiagonal => (c"nwse-resize", XC_BOTTOM_RIGHT_CORNER),
+        CursorIcon::ResizeAntiDiagonal => (c"nesw-resize", XC_BOTTOM_LEFT_CORNER),
+    };
+
+    // Themed cursors match the rest of the desktop, but the core font is always available.
+    match XcursorLibraryLoadCursor(display, name.as_ptr()) {
+        0 => XCreateFontCursor(display, shape),
+        cursor => cursor,
+    }
+}
@@ -0,0 +1,128 @@
+use core::{
+    ffi::{c_long, CStr},
+    ptr, slice, str,
+};
+use std::{ffi::OsString, os::unix::ffi::OsStringExt, path::PathBuf};
+
+use x11::xlib::{self, XFree, XGetWindowProperty, XSendEvent};
+
+use super::{error, intern_atom};
+
+/// Highest version of the protocol understood, older sources are still accepted.
+pub const VERSION: c_long = 5;
+
+#[derive(Copy, Clone)]
+pub struct Drag {
+    pub source: xlib::Window,
+    pub accepted: bool,
+}
+
+/// Whether the source of an `XdndEnter` message offers a list of files.
+pub unsafe fn offers_files(display: *mut xlib::Display, data: &xlib::ClientMessageData) -> bool {
+    let uri_list = intern_atom(display, c"text/uri-list");
+    let source = data.get_long(0) as xlib::Window;
+
+    // Only the first three types fit in the message, the full list is kept in a property.
+    if data.get_long(1) & 1 == 0 {
+        return data.as_longs()[2..5].contains(&(uri_list as _));
+    }
+
+    let type_list = intern_atom(display, c"XdndTypeList");
+    let mut kind = 0;
+    let mut format = 0;
+    let mut length = 0;
+    let mut remaining = 0;
+    let mut data = ptr::null_mut();
+
+    // The source may be gone already, which leaves nothing to read.
+    error::ignore_errors(display, || {
+        XGetWindowProperty(
+            display,
+            source,
+            type_list,
+            0,
+            c_long::MAX / 4,
+            0,
+            xlib::XA_ATOM,
+            &mut kind,
+            &mut format,
+            &mut length,
+            &mut remaining,
+            &mut data,
+        );
+    });
+
+    if data.is_null() {
+        return false;
+    }
+
+    let offered = slice::from_raw_parts(data as *const xlib::Atom, length as _).contains(&uri_list);
+
+    XFree(data as _);
+    offered
+}
+
+pub unsafe fn send(
+    display: *mut xlib::Display,
+    target: xlib::Window,
+    message_type: &CStr,
+    data: [c_long; 5],
+) {
+    let message = xlib::XClientMessageEvent {
+        type_: xlib::ClientMessage,
+        serial: 0,
+        send_event: 1,
+        display,
+        window: target,
+        message_type: intern_atom(display, message_type),
+        format: 32,
+        data: data.into(),
+    };
+
+    // The target may be gone by the time the message arrives.
+    error::ignore_errors(display, || {
+        XSendEvent(display, target, 0, 0, &mut message.into());
+    });
+}
+
+/// Extract the local files from a `text/uri-list`, other kinds of URIs are skipped.
+pub fn parse_uri_list(list: &str) -> impl Iterator<Item = PathBuf> + '_ {
+    list.lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|uri| {
+            let rest = uri.strip_prefix("file://")?;
+
+            // The host is usually empty, but it may name the local machine.
+            let path = &rest[rest.find('/')?..];
+            let bytes = percent_decode(path);
+
+            Some(OsString::from_vec(bytes).into())
+        })
+}
+
+fn percent_decode(text: &str) -> Vec<u8> {
+    let mut decoded = Vec::with_capacity(text.len());
+    let mut rest = text.as_bytes();
+
+    while let [byte, tail @ ..] = rest {
+        let escaped = tail
+            .get(..2)
+            .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|hex| u8::from_str_radix(str::from_utf8(hex).ok()?, 16).ok());
+
+        match (byte, escaped) {
+            (b'%', Some(escaped)) => {
+                decoded.push(escaped);
+                rest = &tail[2..];
+            }
+
+            _ => {
+                decoded.push(*byte);
+                rest = tail;
+            }
+        }
+    }
+
+    decoded
+}
@@ -0,0 +1,161 @@
+use core::{
+    ffi::{c_int, c_void},
+    fmt, mem, ptr,
+    sync::atomic::{AtomicBool, AtomicU16, Ordering},
+};
+use std::{error::Error, sync::Once};
+
+use x11::xlib::{self, XSetErrorHandler, XSetIOErrorHandler, XSync};
+
+// Xlib reports errors through process-wide callbacks, so they can only be stashed globally.
+static PROTOCOL_ERROR: AtomicU16 = AtomicU16::new(0);
+static CONNECTION_LOST: AtomicBool = AtomicBool::new(false);
+
+// Major opcode of the core `CreateWindow` request.
+const X_CREATE_WINDOW: u8 = 1;
+
+// Lets the process survive a broken connection. Only available since libX11 1.7, so it is looked
+// up at runtime instead of refusing to start on older systems.
+type SetIOErrorExitHandler = unsafe extern "C" fn(
+    *mut xlib::Display,
+    Option<unsafe extern "C" fn(*mut xlib::Display, *mut c_void)>,
+    *mut c_void,
+);
+
+#[derive(Debug)]
+pub enum WindowError {
+    NoDisplay,
+    BadVisual,
+    ConnectionLost,
+    Protocol { error_code: u8, request_code: u8 },
+}
+
+impl fmt::Display for WindowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoDisplay => write!(f, "cannot open X display"),
+            Self::BadVisual => write!(f, "no suitable visual"),
+            Self::ConnectionLost => write!(f, "connection to the X server lost"),
+
+            Self::Protocol {
+                error_code,
+                request_code,
+            } => write!(f, "X error {error_code} in request {request_code}"),
+        }
+    }
+}
+
+impl Error for WindowError {}
+
+pub fn install_handlers() {
+    static INSTALL: Once = Once::new();
+
+    INSTALL.call_once(|| unsafe {
+        XSetErrorHandler(Some(on_error));
+        XSetIOErrorHandler(Some(on_io_error));
+    });
+}
+
+/// Without libX11 1.7, a broken connection still terminates the process.
+pub unsafe fn survive_io_errors(display: *mut xlib::Display) {
+    let symbol = libc::dlsym(libc::RTLD_DEFAULT, c"XSetIOErrorExitHandler".as_ptr());
+
+    if symbol.is_null() {
+        return;
+    }
+
+    let set_handler = mem::transmute::<*mut c_void, SetIOErrorExitHandler>(symbol);
+    set_handler(display, Some(on_io_error_exit), ptr::null_mut());
+}
+
+/// Unlike taking the error, this leaves it for the event loop to report.
+pub fn is_connection_lost() -> bool {
+    CONNECTION_LOST.load(Ordering::Acquire)
+}
+
+/// Errors that happened before it was set are put aside, to tell apart the ones caused meanwhile.
+pub struct ErrorTrap {
+    display: *mut xlib::Display,
+    earlier: u16,
+}
+
+impl ErrorTrap {
+    pub unsafe fn set(display: *mut xlib::Display) -> Self {
+        XSync(display, 0);
+
+        Self {
+            display,
+            earlier: PROTOCOL_ERROR.swap(0, Ordering::AcqRel),
+        }
+    }
+
+    /// The first error caused since the trap was set. The earlier ones are left to the event loop.
+    pub unsafe fn finish(self) -> Option<WindowError> {
+        XSync(self.display, 0);
+
+        let error = take_error();
+
+        if self.earlier != 0 {
+            PROTOCOL_ERROR.store(self.earlier, Ordering::Release);
+        }
+
+        error
+    }
+}
+
+/// Requests aimed at windows of other clients fail whenever those go away at the wrong moment,
+/// which is no reason to stop the event loop. Whatever they cause is dropped.
+pub unsafe fn ignore_errors<T>(display: *mut xlib::Display, f: impl FnOnce() -> T) -> T {
+    let trap = ErrorTrap::set(display);
+    let result = f();
+
+    _ = trap.finish();
+    result
+}
+
+/// Every error reaching this is taken as fatal by the event loop, requests that may fail for
+/// reasons outside of our control have to be made through `ignore_errors`.
+pub fn take_error() -> Option<WindowError> {
+    if CONNECTION_LOST.load(Ordering::Acquire) {
+        return Some(WindowError::ConnectionLost);
+    }
+
+    let [error_code, request_code] = match PROTOCOL_ERROR.swap(0, Ordering::AcqRel) {
+        0 => return None,
+        packed => packed.to_le_bytes(),
+    };
+
+    let error = match error_code {
+        xlib::BadMatch if request_code == X_CREATE_WINDOW => WindowError::BadVisual,
+
+        _ => WindowError::Protocol {
+            error_code,
+            request_code,
+        },
+    };
+
+    Some(error)
+}
+
+unsafe extern "C" fn on_error(_: *mut xlib::Display, event: *mut xlib::XErrorEvent) -> c_int {
+    let xlib::XErrorEvent {
+        error_code,
+        request_code,
+        ..
+    } = *event;
+
+    // Keep the first error, the following ones are usually a consequence of it.
+    let packed = u16::from_le_bytes([error_code, request_code]);
+    _ = PROTOCOL_ERROR.compare_exchange(0, packed, Ordering::AcqRel, Ordering::Acquire);
+
+    0
+}
+
+unsafe extern "C" fn on_io_error(_: *mut xlib::Display) -> c_int {
+    CONNECTION_LOST.store(true, Ordering::Release);
+    0
+}
+
+unsafe extern "C" fn on_io_error_exit(_: *mut xlib::Display, _: *mut c_void) {
+    // Returning instead of exiting leaves the display unusable but the process alive.
+}
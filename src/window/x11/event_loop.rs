@@ -0,0 +1,287 @@
+use core::{
+    cell::{Cell, RefCell},
+    ffi::{c_char, c_int, c_uint, CStr},
+    iter, mem, ptr,
+    time::Duration,
+};
+use std::{
+    collections::VecDeque,
+    os::fd::{AsFd, AsRawFd, BorrowedFd, RawFd},
+    rc::{Rc, Weak},
+};
+
+use x11::{
+    xlib::{
+        self, XCheckIfEvent, XCloseDisplay, XConnectionNumber, XDefaultRootWindow, XFilterEvent,
+        XFlush, XFreeCursor, XOpenDisplay, XkbSetDetectableAutoRepeat,
+    },
+    xrandr::{RRNotify, RRScreenChangeNotify, XRRUpdateConfiguration},
+};
+
+use super::{
+    error, intern_atom, monitor, pointer, signal, text::InputMethod, Window, WindowBuilder,
+    WindowError, WindowId, CURSOR_ICON_COUNT,
+};
+use crate::{
+    event::{Event, Input},
+    window::{timeout_millis, LoopState, Monitor, RunMode},
+};
+
+/// Owns the connection to the display, which every window created from it shares.
+pub struct EventLoop {
+    pub(super) connection: Rc<Connection>,
+}
+
+pub(super) struct Connection {
+    pub display: *mut xlib::Display,
+    pub detectable_repeat: bool,
+    pub raw_motion: Option<c_int>,
+    pub randr_event_base: Option<c_int>,
+    pub input_method: Option<InputMethod>,
+    pub invisible_cursor: xlib::Cursor,
+    pub monitors: RefCell<Vec<Monitor>>,
+    pub wm_protocols: xlib::Atom,
+    pub wm_delete_window: xlib::Atom,
+    pub cursors: Cell<[xlib::Cursor; CURSOR_ICON_COUNT]>,
+    pub last_key: Cell<(xlib::Time, c_uint, c_int)>,
+    pub state: LoopState,
+    pub windows: RefCell<Vec<Weak<Window>>>,
+    pub pending: RefCell<VecDeque<(WindowId, Event)>>,
+}
+
+impl EventLoop {
+    pub fn new() -> Result<Self, WindowError> {
+        error::install_handlers();
+        signal::install_handlers();
+
+        let display = unsafe { XOpenDisplay(ptr::null()) };
+
+        if display.is_null() {
+            return Err(WindowError::NoDisplay);
+        }
+
+        let connection = unsafe {
+            let root = XDefaultRootWindow(display);
+
+            error::survive_io_errors(display);
+
+            // Otherwise holding a key down looks like it is being released and pressed again.
+            let mut detectable_repeat = 0;
+            XkbSetDetectableAutoRepeat(display, 1, &mut detectable_repeat);
+
+            Connection {
+                display,
+                detectable_repeat: detectable_repeat != 0,
+                raw_motion: pointer::query_raw_motion(display),
+                randr_event_base: monitor::select_changes(display, root),
+                input_method: InputMethod::open(display),
+                invisible_cursor: pointer::create_invisible_cursor(display, root),
+                monitors: monitor::query(display).into(),
+                wm_protocols: intern_atom(display, c"WM_PROTOCOLS"),
+                wm_delete_window: intern_atom(display, c"WM_DELETE_WINDOW"),
+                cursors: Default::default(),
+                last_key: Default::default(),
+                state: LoopState::new(),
+                windows: Default::default(),
+                pending: Default::default(),
+            }
+        };
+
+        Ok(Self {
+            connection: connection.into(),
+        })
+    }
+
+    pub fn create_window(
+        &self,
+        title: &CStr,
+        width: u32,
+        height: u32,
+    ) -> Result<Rc<Window>, WindowError> {
+        WindowBuilder::new(title).size(width, height).build(self)
+    }
+
+    /// Deliver events until `exit` is called. Events are tagged with the window they belong to,
+    /// `Event::Idle` concerns all of them and has no window.
+    pub fn run(&self, cb: impl FnMut(Option<WindowId>, Event)) -> Result<(), WindowError> {
+        let connection = &*self.connection;
+
+        connection.state.run(
+            || self.poll_events(),
+            |timeout| connection.wait_for_events(timeout),
+            || self.take_error(),
+            cb,
+        )
+    }
+
+    /// Drain the events that are ready without blocking. `Event::Idle` is never produced, the
+    /// caller owns the main loop and decides when to do its work.
+    pub fn poll_events(&self) -> impl Iterator<Item = (WindowId, Event)> + '_ {
+        let connection = &*self.connection;
+
+        iter::from_fn(move || loop {
+            if let Some(event) = connection.pending.borrow_mut().pop_front() {
+                return Some(event);
+            }
+
+            // Being asked to terminate is no different from having every window closed.
+            if signal::take_signal() {
+                connection.close_windows();
+                continue;
+            }
+
+            let mut raw_event = unsafe { mem::zeroed() };
+
+            if !unsafe { connection.peek_event(&mut raw_event) } {
+                return None;
+            }
+
+            unsafe { connection.dispatch(&mut raw_event) };
+        })
+    }
+
+    /// Deliver the events that are ready and send out pending requests, without blocking. For loops
+    /// of other kinds, this is to be called whenever the connection or `signal_fd` becomes
+    /// readable, and always right before waiting on them.
+    #[allow(unused)]
+    pub fn dispatch_pending(&self, mut cb: impl FnMut(WindowId, Event)) -> Result<(), WindowError> {
+        self.poll_events()
+            .for_each(|(window, event)| cb(window, event));
+
+        // Draining the queue leaves nothing buffered, but requests made meanwhile may still be.
+        unsafe { XFlush(self.connection.display) };
+
+        match self.take_error() {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+
+    /// Becomes readable when the process is asked to terminate. Loops of other kinds have to wait
+    /// on it along with the connection, `dispatch_pending` then reports every window as closed.
+    #[allow(unused)]
+    pub fn signal_fd(&self) -> Option<BorrowedFd<'_>> {
+        signal::wake_up_fd().map(|fd| unsafe { BorrowedFd::borrow_raw(fd) })
+    }
+
+    /// Make `run` return once the current callback is done.
+    pub fn exit(&self) {
+        self.connection.state.exit_requested.set(true);
+    }
+
+    pub fn set_run_mode(&self, mode: RunMode) {
+        self.connection.state.run_mode.set(mode);
+    }
+
+    /// Report the first X error since the last call, or a broken connection.
+    pub fn take_error(&self) -> Option<WindowError> {
+        error::take_error()
+    }
+}
+
+/// The connection to the display, to wait on it along with other sources of events.
+impl AsFd for EventLoop {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        unsafe { BorrowedFd::borrow_raw(self.as_raw_fd()) }
+    }
+}
+
+impl AsRawFd for EventLoop {
+    fn as_raw_fd(&self) -> RawFd {
+        unsafe { XConnectionNumber(self.connection.display) }
+    }
+}
+
+impl Connection {
+    pub fn wait_for_events(&self, timeout: Option<Duration>) {
+        let timeout = timeout.map_or(-1, |timeout| {
+            timeout_millis(timeout).min(c_int::MAX as _) as _
+        });
+
+        // Negative descriptors are ignored, in case there is no way to get woken up by signals.
+        let mut fds = [
+            unsafe { XConnectionNumber(self.display) },
+            signal::wake_up_fd().unwrap_or(-1),
+        ]
+        .map(|fd| libc::pollfd {
+            fd,
+            events: libc::POLLIN,
+            revents: 0,
+        });
+
+        unsafe {
+            XFlush(self.display);
+            libc::poll(fds.as_mut_ptr(), fds.len() as _, timeout);
+        }
+    }
+
+    fn close_windows(&self) {
+        for window in self.windows.borrow().iter().filter_map(Weak::upgrade) {
+            let event = Event::Input(Input::Close);
+            self.push_event(window.id(), event);
+        }
+    }
+
+    pub fn push_event(&self, window: WindowId, event: Event) {
+        self.pending.borrow_mut().push_back((window, event));
+    }
+
+    unsafe fn peek_event(&self, event: &mut xlib::XEvent) -> bool {
+        extern "C" fn match_any(
+            _: *mut xlib::Display,
+            _: *mut xlib::XEvent,
+            _: *mut c_char,
+        ) -> c_int {
+            1
+        }
+
+        XCheckIfEvent(self.display, event, Some(match_any), ptr::null_mut()) != 0
+    }
+
+    unsafe fn dispatch(&self, raw_event: &mut xlib::XEvent) {
+        let filtered = self.input_method.is_some() && XFilterEvent(raw_event, 0) != 0;
+        let kind = raw_event.get_type();
+        let is_monitor_change = self.is_monitor_change(kind);
+
+        // Querying the monitors takes several round trips, only do it when they change. Xlib only
+        // cares about the screen size, the other kinds of changes are ignored by it.
+        if is_monitor_change {
+            XRRUpdateConfiguration(raw_event);
+            *self.monitors.borrow_mut() = monitor::query(self.display);
+        }
+
+        // Raw input and monitor changes are reported on the root window, but concern all windows.
+        let is_global = kind == xlib::GenericEvent || is_monitor_change;
+        let target = raw_event.any.window;
+
+        for window in self.windows.borrow().iter().filter_map(Weak::upgrade) {
+            if is_global || **window == target {
+                window.handle_event(raw_event, filtered);
+            }
+        }
+    }
+
+    /// The screen as a whole changing, or any of its outputs and CRTCs.
+    pub fn is_monitor_change(&self, kind: c_int) -> bool {
+        self.randr_event_base.is_some_and(|event_base| {
+            [RRScreenChangeNotify, RRNotify].contains(&(kind - event_base))
+        })
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        unsafe {
+            if let Some(input_method) = &self.input_method {
+                input_method.close();
+            }
+
+            for cursor in self.cursors.get().into_iter().filter(|&cursor| cursor != 0) {
+                XFreeCursor(self.display, cursor);
+            }
+
+            XFreeCursor(self.display, self.invisible_cursor);
+            XCloseDisplay(self.display);
+        }
+    }
+}
@@ -0,0 +1,242 @@
+use core::ffi::c_uint;
+
+use x11::{keysym::*, xlib::KeySym};
+
+use crate::event::Key;
+
+#[allow(non_upper_case_globals)]
+pub fn translate_keysym(keysym: KeySym) -> Option<Key> {
+    let key = match keysym as c_uint {
+        XK_a | XK_A => Key::A,
+        XK_b | XK_B => Key::B,
+        XK_c | XK_C => Key::C,
+        XK_d | XK_D => Key::D,
+        XK_e | XK_E => Key::E,
+        XK_f | XK_F => Key::F,
+        XK_g | XK_G => Key::G,
+        XK_h | XK_H => Key::H,
+        XK_i | XK_I => Key::I,
+        XK_j | XK_J => Key::J,
+        XK_k | XK_K => Key::K,
+        XK_l | XK_L => Key::L,
+        XK_m | XK_M => Key::M,
+        XK_n | XK_N => Key::N,
+        XK_o | XK_O => Key::O,
+        XK_p | XK_P => Key::P,
+        XK_q | XK_Q => Key::Q,
+        XK_r | XK_R => Key::R,
+        XK_s | XK_S => Key::S,
+        XK_t | XK_T => Key::T,
+        XK_u | XK_U => Key::U,
+        XK_v | XK_V => Key::V,
+        XK_w | XK_W => Key::W,
+        XK_x | XK_X => Key::X,
+        XK_y | XK_Y => Key::Y,
+        XK_z | XK_Z => Key::Z,
+
+        XK_0 => Key::Digit0,
+        XK_1 => Key::Digit1,
+        XK_2 => Key::Digit2,
+        XK_3 => Key::Digit3,
+        XK_4 => Key::Digit4,
+        XK_5 => Key::Digit5,
+        XK_6 => Key::Digit6,
+        XK_7 => Key::Digit7,
+        XK_8 => Key::Digit8,
+        XK_9 => Key::Digit9,
+
+        XK_F1 => Key::F1,
+        XK_F2 => Key::F2,
+        XK_F3 => Key::F3,
+        XK_F4 => Key::F4,
+        XK_F5 => Key::F5,
+        XK_F6 => Key::F6,
+        XK_F7 => Key::F7,
+        XK_F8 => Key::F8,
+        XK_F9 => Key::F9,
+        XK_F10 => Key::F10,
+        XK_F11 => Key::F11,
+        XK_F12 => Key::F12,
+
+        XK_Escape => Key::Escape,
+        XK_Tab | XK_ISO_Left_Tab => Key::Tab,
+        XK_Caps_Lock => Key::CapsLock,
+        XK_space => Key::Space,
+        XK_Return => Key::Enter,
+        XK_BackSpace => Key::Backspace,
+        XK_Insert => Key::Insert,
+        XK_Delete => Key::Delete,
+        XK_Home => Key::Home,
+        XK_End => Key::End,
+        XK_Prior => Key::PageUp,
+        XK_Next => Key::PageDown,
+        XK_Left => Key::Left,
+        XK_Right => Key::Right,
+        XK_Up => Key::Up,
+        XK_Down => Key::Down,
+
+        XK_Shift_L => Key::LeftShift,
+        XK_Shift_R => Key::RightShift,
+        XK_Control_L => Key::LeftControl,
+        XK_Control_R => Key::RightControl,
+        XK_Alt_L | XK_Meta_L => Key::LeftAlt,
+        XK_Alt_R | XK_Meta_R | XK_ISO_Level3_Shift => Key::RightAlt,
+        XK_Super_L => Key::LeftSuper,
+        XK_Super_R => Key::RightSuper,
+        XK_Menu => Key::Menu,
+
+        XK_minus => Key::Minus,
+        XK_equal => Key::Equal,
+        XK_bracketleft => Key::LeftBracket,
+        XK_bracketright => Key::RightBracket,
+        XK_backslash => Key::Backslash,
+        XK_semicolon => Key::Semicolon,
+        XK_apostrophe => Key::Apostrophe,
+        XK_grave => Key::Grave,
+        XK_comma => Key::Comma,
+        XK_period => Key::Period,
+        XK_slash => Key::Slash,
+
+        XK_Print => Key::PrintScreen,
+        XK_Scroll_Lock => Key::ScrollLock,
+        XK_Pause => Key::Pause,
+        XK_Num_Lock => Key::NumLock,
+
+        // Without Num Lock the keypad reports navigation keysyms instead of digits.
+        XK_KP_0 | XK_KP_Insert => Key::Numpad0,
+        XK_KP_1 | XK_KP_End => Key::Numpad1,
+        XK_KP_2 | XK_KP_Down => Key::Numpad2,
+        XK_KP_3 | XK_KP_Next => Key::Numpad3,
+        XK_KP_4 | XK_KP_Left => Key::Numpad4,
+        XK_KP_5 | XK_KP_Begin => Key::Numpad5,
+        XK_KP_6 | XK_KP_Right => Key::Numpad6,
+        XK_KP_7 | XK_KP_Home => Key::Numpad7,
+        XK_KP_8 | XK_KP_Up => Key::Numpad8,
+        XK_KP_9 | XK_KP_Prior => Key::Numpad9,
+        XK_KP_Add => Key::NumpadAdd,
+        XK_KP_Subtract => Key::NumpadSubtract,
+        XK_KP_Multiply => Key::NumpadMultiply,
+        XK_KP_Divide => Key::NumpadDivide,
+        XK_KP_Decimal | XK_KP_Delete => Key::NumpadDecimal,
+        XK_KP_Enter => Key::NumpadEnter,
+
+        _ => return None,
+    };
+
+    Some(key)
+}
+
+/// Name the physical key after what it is on a US layout, regardless of the active one.
+pub fn translate_keycode(keycode: c_uint) -> Option<Key> {
+    // The X server numbers keys after the evdev codes of the kernel, shifted by 8.
+    let key = match keycode.checked_sub(8)? {
+        1 => Key::Escape,
+        2 => Key::Digit1,
+        3 => Key::Digit2,
+        4 => Key::Digit3,
+        5 => Key::Digit4,
+        6 => Key::Digit5,
+        7 => Key::Digit6,
+        8 => Key::Digit7,
+        9 => Key::Digit8,
+        10 => Key::Digit9,
+        11 => Key::Digit0,
+        12 => Key::Minus,
+        13 => Key::Equal,
+        14 => Key::Backspace,
+        15 => Key::Tab,
+        16 => Key::Q,
+        17 => Key::W,
+        18 => Key::E,
+        19 => Key::R,
+        20 => Key::T,
+        21 => Key::Y,
+        22 => Key::U,
+        23 => Key::I,
+        24 => Key::O,
+        25 => Key::P,
+        26 => Key::LeftBracket,
+        27 => Key::RightBracket,
+        28 => Key::Enter,
+        29 => Key::LeftControl,
+        30 => Key::A,
+        31 => Key::S,
+        32 => Key::D,
+        33 => Key::F,
+        34 => Key::G,
+        35 => Key::H,
+        36 => Key::J,
+        37 => Key::K,
+        38 => Key::L,
+        39 => Key::Semicolon,
+        40 => Key::Apostrophe,
+        41 => Key::Grave,
+        42 => Key::LeftShift,
+        43 => Key::Backslash,
+        44 => Key::Z,
+        45 => Key::X,
+        46 => Key::C,
+        47 => Key::V,
+        48 => Key::B,
+        49 => Key::N,
+        50 => Key::M,
+        51 => Key::Comma,
+        52 => Key::Period,
+        53 => Key::Slash,
+        54 => Key::RightShift,
+        55 => Key::NumpadMultiply,
+        56 => Key::LeftAlt,
+        57 => Key::Space,
+        58 => Key::CapsLock,
+        59 => Key::F1,
+        60 => Key::F2,
+        61 => Key::F3,
+        62 => Key::F4,
+        63 => Key::F5,
+        64 => Key::F6,
+        65 => Key::F7,
+        66 => Key::F8,
+        67 => Key::F9,
+        68 => Key::F10,
+        69 => Key::NumLock,
+        70 => Key::ScrollLock,
+        71 => Key::Numpad7,
+        72 => Key::Numpad8,
+        73 => Key::Numpad9,
+        74 => Key::NumpadSubtract,
+        75 => Key::Numpad4,
+        76 => Key::Numpad5,
+        77 => Key::Numpad6,
+        78 => Key::NumpadAdd,
+        79 => Key::Numpad1,
+        80 => Key::Numpad2,
+        81 => Key::Numpad3,
+        82 => Key::Numpad0,
+        83 => Key::NumpadDecimal,
+        87 => Key::F11,
+        88 => Key::F12,
+        96 => Key::NumpadEnter,
+        97 => Key::RightControl,
+        98 => Key::NumpadDivide,
+        99 => Key::PrintScreen,
+        100 => Key::RightAlt,
+        102 => Key::Home,
+        103 => Key::Up,
+        104 => Key::PageUp,
+        105 => Key::Left,
+        106 => Key::Right,
+        107 => Key::End,
+        108 => Key::Down,
+        109 => Key::PageDown,
+        110 => Key::Insert,
+        111 => Key::Delete,
+        119 => Key::Pause,
+        125 => Key::LeftSuper,
+        126 => Key::RightSuper,
+        127 => Key::Menu,
+
+        _ => return None,
+    };
+
+    Some(key)
+}
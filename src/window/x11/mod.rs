@@ -0,0 +1,990 @@
+use core::{
+    cell::{Cell, RefCell},
+    ffi::{c_char, c_int, c_long, c_uint, c_ulong, CStr},
+    mem, ptr, slice,
+    time::Duration,
+};
+use std::{
+    rc::{Rc, Weak},
+    time::Instant,
+};
+
+use x11::xlib::{
+    self, XChangeProperty, XCheckIfEvent, XConvertSelection, XDefaultRootWindow, XDefineCursor,
+    XDestroyWindow, XEventsQueued, XFlush, XFree, XFreeColormap, XGetSelectionOwner,
+    XGetWindowAttributes, XGetWindowProperty, XGrabPointer, XInternAtom, XLookupKeysym, XPeekEvent,
+    XSendEvent, XSetSelectionOwner, XStoreName, XTranslateCoordinates, XUngrabPointer,
+    XWarpPointer,
+};
+
+use super::{CursorIcon, Monitor};
+use crate::{
+    event::{Button, Event, Input},
+    utils,
+};
+
+use clipboard::Selection;
+use dnd::Drag;
+use event_loop::Connection;
+use text::InputContext;
+
+pub use builder::WindowBuilder;
+pub use error::WindowError;
+pub use event_loop::EventLoop;
+
+mod builder;
+mod clipboard;
+mod cursor;
+mod dnd;
+mod error;
+mod event_loop;
+mod keyboard;
+mod monitor;
+mod pointer;
+mod signal;
+mod text;
+
+const CURSOR_ICON_COUNT: usize = mem::variant_count::<CursorIcon>();
+const SELECTION_TIMEOUT: Duration = Duration::from_millis(500);
+
+// Missing from the bindings, makes `XEventsQueued` read whatever already arrived.
+const QUEUED_AFTER_READING: c_int = 1;
+
+/// Tags events with the window they belong to.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct WindowId(xlib::Window);
+
+pub struct Window {
+    inner: xlib::Window,
+    display: *mut xlib::Display,
+    connection: Rc<Connection>,
+    transparent: bool,
+    colormap: xlib::Colormap,
+    size: Cell<[u32; 2]>,
+    position: Cell<[i32; 2]>,
+    mapped: Cell<bool>,
+    obscured: Cell<bool>,
+    focused: Cell<bool>,
+    fullscreen: Cell<bool>,
+    scale_factor: Cell<f64>,
+    cursor_grabbed: Cell<bool>,
+    motion_remainder: Cell<[f64; 2]>,
+    cursor_icon: Cell<CursorIcon>,
+    cursor_visible: Cell<bool>,
+    input_context: Option<InputContext>,
+    text_input: Cell<bool>,
+    held_keys: RefCell<[bool; 256]>,
+    selections: RefCell<[Option<String>; 2]>,
+    drag: Cell<Option<Drag>>,
+}
+
+utils::wrap! { Window, xlib::Window }
+
+impl Window {
+    pub fn id(&self) -> WindowId {
+        WindowId(**self)
+    }
+
+    pub fn display(&self) -> *mut xlib::Display {
+        self.display
+    }
+
+    pub fn inner_size(&self) -> [u32; 2] {
+        let mut attributes = unsafe { mem::zeroed() };
+
+        unsafe { XGetWindowAttributes(self.display, **self, &mut attributes) };
+        [attributes.width as _, attributes.height as _]
+    }
+
+    /// Whether the alpha channel blends the window with whatever is behind it. That only happens
+    /// while a compositor is running, otherwise it is just ignored.
+    pub fn is_transparent(&self) -> bool {
+        self.transparent
+    }
+
+    pub fn set_title(&self, title: &CStr) {
+        unsafe {
+            store_title(self.display, **self, title);
+            XFlush(self.display);
+        }
+    }
+
+    /// Pixels are ARGB, row after row. It is up to the window manager to scale it as needed.
+    #[allow(unused)]
+    pub fn set_icon(&self, width: u32, height: u32, pixels: &[u32]) {
+        assert_eq!(
+            pixels.len(),
+            (width * height) as usize,
+            "Icon size mismatch"
+        );
+
+        // Each pixel is a `CARDINAL`, which Xlib wants as a long even if it is 64 bits wide.
+        let data = [width, height]
+            .iter()
+            .chain(pixels)
+            .map(|&value| value as c_ulong)
+            .collect::<Vec<_>>();
+
+        unsafe {
+            XChangeProperty(
+                self.display,
+                **self,
+                intern_atom(self.display, c"_NET_WM_ICON"),
+                xlib::XA_CARDINAL,
+                32,
+                xlib::PropModeReplace,
+                data.as_ptr() as _,
+                data.len() as _,
+            );
+
+            XFlush(self.display);
+        }
+    }
+
+    pub fn is_fullscreen(&self) -> bool {
+        self.fullscreen.get()
+    }
+
+    /// The window manager takes care of the geometry, the new size is reported as a resize. It may
+    /// also refuse, `is_fullscreen` only changes once it agrees.
+    pub fn set_fullscreen(&self, fullscreen: bool) {
+        if self.fullscreen.get() == fullscreen {
+            return;
+        }
+
+        unsafe {
+            let fullscreen_state = intern_atom(self.display, c"_NET_WM_STATE_FULLSCREEN");
+
+            // Action (remove or add), first property, second property, source (application).
+            let data = [fullscreen as _, fullscreen_state as _, 0, 1, 0];
+
+            self.send_to_window_manager(c"_NET_WM_STATE", data);
+            XFlush(self.display);
+        }
+    }
+
+    #[allow(unused)]
+    pub fn set_cursor(&self, icon: CursorIcon) {
+        self.cursor_icon.set(icon);
+        self.update_cursor();
+    }
+
+    #[allow(unused)]
+    pub fn set_cursor_visible(&self, visible: bool) {
+        self.cursor_visible.set(visible);
+        self.update_cursor();
+    }
+
+    /// Confine and hide the cursor, reporting relative motion as `Input::MouseDelta` instead of
+    /// `Input::MouseMotion`. The grab is released when the window loses focus.
+    #[allow(unused)]
+    pub fn set_cursor_grab(&self, grab: bool) -> bool {
+        if self.cursor_grabbed.get() == grab {
+            return grab;
+        }
+
+        if !grab {
+            unsafe { self.release_cursor() };
+            return false;
+        }
+
+        let mask = xlib::ButtonPressMask | xlib::ButtonReleaseMask | xlib::PointerMotionMask;
+
+        let status = unsafe {
+            XGrabPointer(
+                self.display,
+                **self,
+                1,
+                mask as _,
+                xlib::GrabModeAsync,
+                xlib::GrabModeAsync,
+                **self,
+                self.connection.invisible_cursor,
+                xlib::CurrentTime,
+            )
+        };
+
+        // The window may not be viewable yet, or someone else may be holding the pointer.
+        if status != xlib::GrabSuccess {
+            return false;
+        }
+
+        unsafe {
+            match self.connection.raw_motion {
+                Some(_) => pointer::select_raw_motion(self.display, true),
+                None => self.warp_to_center(),
+            }
+
+            XFlush(self.display);
+        }
+
+        self.cursor_grabbed.set(true);
+        true
+    }
+
+    #[allow(unused)]
+    pub fn is_cursor_grabbed(&self) -> bool {
+        self.cursor_grabbed.get()
+    }
+
+    /// Deliver typed text as `Input::Text`, composed by the input method from dead keys, compose
+    /// sequences or whatever else it supports. Key events are reported regardless.
+    #[allow(unused)]
+    pub fn set_text_input(&self, enabled: bool) {
+        self.text_input.set(enabled);
+        self.update_input_method_focus();
+    }
+
+    #[allow(unused)]
+    pub fn set_clipboard(&self, text: &str) {
+        self.set_selection(Selection::Clipboard, text);
+    }
+
+    /// Ask the owner of the clipboard for its contents, giving up if it takes too long to answer.
+    #[allow(unused)]
+    pub fn clipboard(&self) -> Option<String> {
+        self.selection(Selection::Clipboard)
+    }
+
+    #[allow(unused)]
+    pub fn set_primary_selection(&self, text: &str) {
+        self.set_selection(Selection::Primary, text);
+    }
+
+    #[allow(unused)]
+    pub fn primary_selection(&self) -> Option<String> {
+        self.selection(Selection::Primary)
+    }
+
+    #[allow(unused)]
+    pub fn monitors(&self) -> Vec<Monitor> {
+        self.connection.monitors.borrow().clone()
+    }
+
+    /// The monitor with the center of the window, or the primary one if it is off-screen.
+    pub fn current_monitor(&self) -> Option<Monitor> {
+        let [width, height] = self.size.get();
+        let [left, top] = self.position.get();
+        let [x, y] = [left + width as i32 / 2, top + height as i32 / 2];
+        let monitors = self.connection.monitors.borrow();
+
+        let containing = monitors.iter().find(|monitor| {
+            let [left, top] = monitor.position;
+            let [width, height] = monitor.size.map(|length| length as i32);
+
+            (left..left + width).contains(&x) && (top..top + height).contains(&y)
+        });
+
+        containing
+            .or_else(|| monitors.iter().find(|monitor| monitor.primary))
+            .cloned()
+    }
+
+    /// Follows the monitor the window is on, changes are reported as `Event::ScaleFactorChanged`.
+    #[allow(unused)]
+    pub fn scale_factor(&self) -> f64 {
+        self.scale_factor.get()
+    }
+
+    pub fn request_redraw(&self) {
+        self.connection.state.redraw_requested.set(true);
+    }
+
+    fn update_cursor(&self) {
+        let icon = self.cursor_icon.get();
+        let cursors = &self.connection.cursors;
+        let mut loaded = cursors.get();
+
+        // Cursors are loaded on first use and kept until the connection goes away.
+        if loaded[icon as usize] == 0 {
+            loaded[icon as usize] = unsafe { cursor::load(self.display, icon) };
+            cursors.set(loaded);
+        }
+
+        let cursor = match self.cursor_visible.get() {
+            true => loaded[icon as usize],
+            false => self.connection.invisible_cursor,
+        };
+
+        unsafe {
+            XDefineCursor(self.display, **self, cursor);
+            XFlush(self.display);
+        }
+    }
+
+    unsafe fn release_cursor(&self) {
+        if self.connection.raw_motion.is_some() {
+            pointer::select_raw_motion(self.display, false);
+        }
+
+        XUngrabPointer(self.display, xlib::CurrentTime);
+        XFlush(self.display);
+        self.cursor_grabbed.set(false);
+    }
+
+    unsafe fn warp_to_center(&self) {
+        let [width, height] = self.size.get();
+        let center = [width as i32 / 2, height as i32 / 2];
+
+        XWarpPointer(self.display, 0, **self, 0, 0, 0, 0, center[0], center[1]);
+    }
+
+    fn set_selection(&self, selection: Selection, text: &str) {
+        let owner = unsafe {
+            let atom = intern_atom(self.display, selection.name());
+
+            XSetSelectionOwner(self.display, atom, **self, xlib::CurrentTime);
+            XGetSelectionOwner(self.display, atom)
+        };
+
+        // The contents are kept around to answer requests until someone else takes over.
+        if owner == **self {
+            self.selections.borrow_mut()[selection as usize] = Some(text.to_owned());
+        }
+    }
+
+    fn selection(&self, selection: Selection) -> Option<String> {
+        let owner = unsafe {
+            let atom = intern_atom(self.display, selection.name());
+            XGetSelectionOwner(self.display, atom)
+        };
+
+        let local_owner = self
+            .connection
+            .windows
+            .borrow()
+            .iter()
+            .filter_map(Weak::upgrade)
+            .find(|window| ***window == owner);
+
+        // Asking any window of ours through the server would deadlock, nobody would answer.
+        if let Some(window) = local_owner {
+            return window.selections.borrow()[selection as usize].clone();
+        }
+
+        let deadline = Instant::now() + SELECTION_TIMEOUT;
+        let utf8_string = unsafe { intern_atom(self.display, c"UTF8_STRING") };
+
+        // Not every client speaks UTF-8, fall back to Latin-1.
+        [utf8_string, xlib::XA_STRING]
+            .into_iter()
+            .find_map(|target| unsafe { self.convert_selection(selection, target, deadline) })
+    }
+
+    unsafe fn convert_selection(
+        &self,
+        selection: Selection,
+        target: xlib::Atom,
+        deadline: Instant,
+    ) -> Option<String> {
+        let selection = intern_atom(self.display, selection.name());
+        let property = intern_atom(self.display, c"ASH_SANDBOX_SELECTION");
+        let mut raw_event = mem::zeroed::<xlib::XEvent>();
+
+        XConvertSelection(
+            self.display,
+            selection,
+            target,
+            property,
+            **self,
+            xlib::CurrentTime,
+        );
+
+        // Other notifications (like the ones for drops) are left for the event loop.
+        let mut wanted = (**self, selection, target);
+
+        loop {
+            let found = XCheckIfEvent(
+                self.display,
+                &mut raw_event,
+                Some(is_selection_notify),
+                &mut wanted as *mut _ as _,
+            );
+
+            if found != 0 {
+                break;
+            }
+
+            let now = Instant::now();
+
+            // No answer can arrive over a broken connection, and nobody wants the contents anymore
+            // if the process is about to terminate.
+            if now >= deadline || error::is_connection_lost() || signal::is_pending() {
+                return None;
+            }
+
+            self.connection.wait_for_events(Some(deadline - now));
+        }
+
+        // The owner refuses to convert the selection, or there is none.
+        if raw_event.selection.property == 0 {
+            return None;
+        }
+
+        clipboard::take_property(self.display, **self, property)
+    }
+
+    unsafe fn send_to_window_manager(&self, message_type: &CStr, data: [c_long; 5]) {
+        let message = xlib::XClientMessageEvent {
+            type_: xlib::ClientMessage,
+            serial: 0,
+            send_event: 1,
+            display: self.display,
+            window: **self,
+            message_type: intern_atom(self.display, message_type),
+            format: 32,
+            data: data.into(),
+        };
+
+        let mask = xlib::SubstructureRedirectMask | xlib::SubstructureNotifyMask;
+        let root = XDefaultRootWindow(self.display);
+
+        XSendEvent(self.display, root, 0, mask, &mut message.into());
+    }
+
+    unsafe fn handle_event(&self, raw_event: &xlib::XEvent, filtered: bool) {
+        let is_key = matches!(raw_event.get_type(), xlib::KeyPress | xlib::KeyRelease);
+
+        // The input method keeps the events it consumes, except for keys which are still reported.
+        if filtered && !is_key {
+            return;
+        }
+
+        match raw_event.get_type() {
+            xlib::ClientMessage => self.handle_client_message(&raw_event.client_message),
+
+            xlib::KeyPress | xlib::KeyRelease => {
+                let mut key_event = raw_event.key;
+                let pressed = raw_event.get_type() == xlib::KeyPress;
+
+                // The key is still held, the press that follows is flagged as a repeat.
+                if !pressed && self.is_repeat_release(&key_event) {
+                    return;
+                }
+
+                // The input method may hand a filtered key back, don't report it twice.
+                let id = (key_event.time, key_event.keycode, key_event.type_);
+                let is_new = self.connection.last_key.replace(id) != id;
+
+                // Text committed by the input method arrives as a key event without a keycode.
+                let (symbol, position) = match key_event.keycode {
+                    0 => (None, None),
+                    keycode => (
+                        keyboard::translate_keysym(XLookupKeysym(&mut key_event, 0)),
+                        keyboard::translate_keycode(keycode),
+                    ),
+                };
+
+                if is_new && (symbol.is_some() || position.is_some()) {
+                    // Held keys keep producing presses, without releases in between.
+                    let held = &mut self.held_keys.borrow_mut()[key_event.keycode as usize];
+                    let was_held = mem::replace(held, pressed);
+                    let repeat = pressed && was_held;
+
+                    let event = Event::Input(Input::Key {
+                        symbol,
+                        position,
+                        pressed,
+                        repeat,
+                    });
+
+                    self.push_event(event);
+                }
+
+                if !pressed || filtered || !self.text_input.get() {
+                    return;
+                }
+
+                let text = match &self.input_context {
+                    Some(input_context) => input_context.lookup(&mut key_event),
+                    None => None,
+                };
+
+                if let Some(text) = text {
+                    let event = Event::Input(Input::Text(text));
+                    self.push_event(event);
+                }
+            }
+
+            xlib::ButtonPress | xlib::ButtonRelease => {
+                let xlib::XButtonEvent { button, x, y, .. } = raw_event.button;
+                let pressed = raw_event.get_type() == xlib::ButtonPress;
+
+                let input = match (button, pressed) {
+                    // The wheel is reported as buttons 4 to 7, releases carry no information.
+                    (4..=7, false) => return,
+                    (4, true) => Input::MouseWheel { delta: [0, 1] },
+                    (5, true) => Input::MouseWheel { delta: [0, -1] },
+                    (6, true) => Input::MouseWheel { delta: [-1, 0] },
+                    (7, true) => Input::MouseWheel { delta: [1, 0] },
+
+                    _ => {
+                        let Some(button) = translate_button(button) else {
+                            return;
+                        };
+
+                        let position = [x, y];
+                        Input::MouseButton {
+                            button,
+                            pressed,
+                            position,
+                        }
+                    }
+                };
+
+                let event = Event::Input(input);
+                self.push_event(event);
+            }
+
+            xlib::MotionNotify => {
+                let xlib::XMotionEvent { x, y, .. } = raw_event.motion;
+                let position = [x, y];
+
+                let input = match (self.cursor_grabbed.get(), self.connection.raw_motion) {
+                    (false, _) => Input::MouseMotion { position },
+
+                    // Raw events already carry the motion, without acceleration.
+                    (true, Some(_)) => return,
+
+                    // Without raw events, keep the cursor centered and measure how far it drifts.
+                    (true, None) => {
+                        let [width, height] = self.size.get();
+                        let delta = [x - width as i32 / 2, y - height as i32 / 2];
+
+                        // Warping produces a motion event too, which lands exactly on the center.
+                        if delta == [0, 0] {
+                            return;
+                        }
+
+                        self.warp_to_center();
+                        Input::MouseDelta { delta }
+                    }
+                };
+
+                let event = Event::Input(input);
+                self.push_event(event);
+            }
+
+            xlib::GenericEvent => {
+                let mut cookie = raw_event.generic_event_cookie;
+
+                if !self.cursor_grabbed.get()
+                    || Some(cookie.extension) != self.connection.raw_motion
+                {
+                    return;
+                }
+
+                let Some(raw_delta) = pointer::read_raw_motion(self.display, &mut cookie) else {
+                    return;
+                };
+
+                // Fractions of a pixel add up over several events, slow motion would be lost.
+                let [x, y] = self.motion_remainder.get();
+                let total = [raw_delta[0] + x, raw_delta[1] + y];
+                let rounded = total.map(f64::round);
+
+                self.motion_remainder
+                    .set([total[0] - rounded[0], total[1] - rounded[1]]);
+
+                if rounded == [0.0; 2] {
+                    return;
+                }
+
+                let delta = rounded.map(|delta| delta as _);
+                let event = Event::Input(Input::MouseDelta { delta });
+                self.push_event(event);
+            }
+
+            xlib::SelectionRequest => {
+                let request = raw_event.selection_request;
+                let selection = Selection::from_atom(self.display, request.selection);
+
+                let selections = self.selections.borrow();
+                let text =
+                    selection.and_then(|selection| selections[selection as usize].as_deref());
+
+                error::ignore_errors(self.display, || {
+                    clipboard::serve(self.display, &request, text);
+                });
+            }
+
+            // Fullscreen may be toggled by the window manager too, or not at all when asked to.
+            xlib::PropertyNotify
+                if raw_event.property.atom == intern_atom(self.display, c"_NET_WM_STATE") =>
+            {
+                self.fullscreen.set(self.query_fullscreen());
+            }
+
+            xlib::SelectionClear => {
+                let atom = raw_event.selection_clear.selection;
+
+                // Someone else owns the selection now, there is no point in keeping the contents.
+                if let Some(selection) = Selection::from_atom(self.display, atom) {
+                    self.selections.borrow_mut()[selection as usize] = None;
+                }
+            }
+
+            xlib::SelectionNotify => {
+                let notify = raw_event.selection;
+
+                // Clipboard contents are picked up where they are asked for, drops end up here.
+                if notify.selection != intern_atom(self.display, c"XdndSelection") {
+                    return;
+                }
+
+                let list = match notify.property {
+                    0 => None,
+                    property => clipboard::take_property(self.display, **self, property),
+                };
+
+                for path in list.iter().flat_map(|list| dnd::parse_uri_list(list)) {
+                    let event = Event::FileDropped(path);
+                    self.push_event(event);
+                }
+
+                self.finish_drop();
+            }
+
+            xlib::FocusIn | xlib::FocusOut => {
+                let mode = raw_event.focus_change.mode;
+                let focused = raw_event.get_type() == xlib::FocusIn;
+
+                // Keyboard grabs (e.g. by the window manager) don't mean the focus is gone.
+                if matches!(mode, xlib::NotifyGrab | xlib::NotifyUngrab) {
+                    return;
+                }
+
+                if !focused && self.cursor_grabbed.get() {
+                    self.release_cursor();
+                }
+
+                // Keys released elsewhere are never reported here.
+                if !focused {
+                    *self.held_keys.borrow_mut() = [false; 256];
+                }
+
+                if self.focused.replace(focused) != focused {
+                    let event = Event::Focus(focused);
+                    self.push_event(event);
+                }
+
+                self.update_input_method_focus();
+            }
+
+            xlib::Expose => self.request_redraw(),
+
+            xlib::ConfigureNotify => {
+                let xlib::XConfigureEvent {
+                    x,
+                    y,
+                    width,
+                    height,
+                    send_event,
+                    ..
+                } = raw_event.configure;
+
+                let size = [width as _, height as _];
+
+                // Moving the window also triggers this event, only report actual resizes.
+                let resized = self.size.replace(size) != size;
+
+                // Window managers tell where the window is on the screen, but the server only tells
+                // where it is inside of the frame around it.
+                let position = match send_event {
+                    0 => self.query_position(),
+                    _ => [x, y],
+                };
+
+                let moved = self.position.replace(position) != position;
+
+                // Moving over to another monitor may change the scale factor as well.
+                if moved || resized {
+                    self.update_scale_factor();
+                }
+
+                if !resized {
+                    return;
+                }
+
+                let event = Event::Resized(size);
+                self.push_event(event);
+            }
+
+            xlib::MapNotify | xlib::UnmapNotify => {
+                let mapped = raw_event.get_type() == xlib::MapNotify;
+                self.update_visibility(mapped, self.obscured.get());
+            }
+
+            xlib::VisibilityNotify => {
+                let obscured = raw_event.visibility.state == xlib::VisibilityFullyObscured;
+                self.update_visibility(self.mapped.get(), obscured);
+            }
+
+            kind if self.connection.is_monitor_change(kind) => {
+                self.update_scale_factor();
+            }
+
+            _ => {}
+        }
+    }
+
+    unsafe fn handle_client_message(&self, message: &xlib::XClientMessageEvent) {
+        let atom = |name| intern_atom(self.display, name);
+        let message_type = message.message_type;
+        let data = &message.data;
+
+        // Every message of the protocol names its source first, only the one that entered counts.
+        let source = data.get_long(0) as xlib::Window;
+        let drag = self.drag.get().filter(|drag| drag.source == source);
+
+        if message_type == atom(c"XdndEnter") {
+            // Sources speaking a newer version of the protocol must be ignored.
+            if data.get_long(1) >> 24 > dnd::VERSION {
+                return;
+            }
+
+            let accepted = dnd::offers_files(self.display, data);
+
+            self.drag.set(Some(Drag { source, accepted }));
+            self.push_event(Event::DragEntered);
+        } else if message_type == atom(c"XdndPosition") {
+            let Some(drag) = drag else {
+                return;
+            };
+
+            let action = match drag.accepted {
+                true => atom(c"XdndActionCopy"),
+                false => 0,
+            };
+
+            // Target, accepted, rectangle to stay quiet in (none), action.
+            let data = [**self as _, drag.accepted as _, 0, 0, action as _];
+            dnd::send(self.display, drag.source, c"XdndStatus", data);
+        } else if message_type == atom(c"XdndLeave") {
+            if drag.is_some() {
+                self.drag.set(None);
+                self.push_event(Event::DragLeft);
+            }
+        } else if message_type == atom(c"XdndDrop") {
+            let Some(drag) = drag else {
+                return;
+            };
+
+            if !drag.accepted {
+                self.finish_drop();
+                return;
+            }
+
+            let selection = atom(c"XdndSelection");
+            let target = atom(c"text/uri-list");
+            let property = atom(c"ASH_SANDBOX_DROP");
+            let time = data.get_long(2) as _;
+
+            // The files are reported once the source hands them over.
+            XConvertSelection(self.display, selection, target, property, **self, time);
+            self.drag.set(Some(drag));
+        } else if message_type == self.connection.wm_protocols {
+            // Only the close button is asked for, but window managers may send other protocols.
+            if data.get_long(0) as xlib::Atom == self.connection.wm_delete_window {
+                let event = Event::Input(Input::Close);
+                self.push_event(event);
+            }
+        }
+    }
+
+    unsafe fn finish_drop(&self) {
+        let Some(drag) = self.drag.take() else {
+            return;
+        };
+
+        let action = match drag.accepted {
+            true => intern_atom(self.display, c"XdndActionCopy"),
+            false => 0,
+        };
+
+        // Target, accepted, action.
+        let data = [**self as _, drag.accepted as _, action as _, 0, 0];
+
+        dnd::send(self.display, drag.source, c"XdndFinished", data);
+        self.push_event(Event::DragLeft);
+    }
+
+    /// Without detectable auto-repeat, held keys are released and pressed again at the same time.
+    unsafe fn is_repeat_release(&self, release: &xlib::XKeyEvent) -> bool {
+        if self.connection.detectable_repeat
+            || XEventsQueued(self.display, QUEUED_AFTER_READING) == 0
+        {
+            return false;
+        }
+
+        let mut next = mem::zeroed::<xlib::XEvent>();
+        XPeekEvent(self.display, &mut next);
+
+        next.get_type() == xlib::KeyPress
+            && next.key.keycode == release.keycode
+            && next.key.time == release.time
+    }
+
+    unsafe fn query_fullscreen(&self) -> bool {
+        let state = intern_atom(self.display, c"_NET_WM_STATE");
+        let fullscreen = intern_atom(self.display, c"_NET_WM_STATE_FULLSCREEN");
+        let (mut kind, mut format, mut length, mut remaining) = (0, 0, 0, 0);
+        let mut data = ptr::null_mut();
+
+        XGetWindowProperty(
+            self.display,
+            **self,
+            state,
+            0,
+            c_long::MAX / 4,
+            0,
+            xlib::XA_ATOM,
+            &mut kind,
+            &mut format,
+            &mut length,
+            &mut remaining,
+            &mut data,
+        );
+
+        if data.is_null() {
+            return false;
+        }
+
+        // Properties of 32 bits are handed over as longs, whatever their actual size.
+        let atoms = slice::from_raw_parts(data as *const xlib::Atom, length as _);
+        let found = format == 32 && atoms.contains(&fullscreen);
+
+        XFree(data as _);
+        found
+    }
+
+    /// Costs a round trip, prefer the position window managers report whenever there is one.
+    fn query_position(&self) -> [i32; 2] {
+        let (mut x, mut y, mut child) = (0, 0, 0);
+
+        unsafe {
+            let root = XDefaultRootWindow(self.display);
+            XTranslateCoordinates(self.display, **self, root, 0, 0, &mut x, &mut y, &mut child);
+        }
+
+        [x, y]
+    }
+
+    fn update_scale_factor(&self) {
+        let scale_factor = self
+            .current_monitor()
+            .map_or(1.0, |monitor| monitor.scale_factor);
+
+        if self.scale_factor.replace(scale_factor) != scale_factor {
+            let event = Event::ScaleFactorChanged(scale_factor);
+            self.push_event(event);
+        }
+    }
+
+    fn update_visibility(&self, mapped: bool, obscured: bool) {
+        let was_mapped = self.mapped.replace(mapped);
+        let was_obscured = self.obscured.replace(obscured);
+
+        let was_visible = was_mapped && !was_obscured;
+        let is_visible = mapped && !obscured;
+
+        if was_visible != is_visible {
+            let event = Event::Visibility(is_visible);
+            self.push_event(event);
+        }
+    }
+
+    fn update_input_method_focus(&self) {
+        if let Some(input_context) = &self.input_context {
+            let focused = self.focused.get() && self.text_input.get();
+            unsafe { input_context.set_focus(focused) };
+        }
+    }
+
+    fn push_event(&self, event: Event) {
+        self.connection.push_event(self.id(), event);
+    }
+}
+
+impl Drop for Window {
+    fn drop(&mut self) {
+        unsafe {
+            if self.cursor_grabbed.get() {
+                self.release_cursor();
+            }
+
+            if let Some(input_context) = &self.input_context {
+                input_context.destroy();
+            }
+
+            XDestroyWindow(self.display, **self);
+
+            if self.colormap != 0 {
+                XFreeColormap(self.display, self.colormap);
+            }
+
+            XFlush(self.display);
+        }
+
+        // The connection only keeps weak references, forget the ones that are gone.
+        let mut windows = self.connection.windows.borrow_mut();
+        windows.retain(|window| window.strong_count() > 0);
+
+        // Nobody is left to receive what was still queued up for this window.
+        let id = self.id();
+        let mut pending = self.connection.pending.borrow_mut();
+        pending.retain(|(window, _)| *window != id);
+    }
+}
+
+/// Matches the notification for a given requestor, selection and target.
+unsafe extern "C" fn is_selection_notify(
+    _: *mut xlib::Display,
+    event: *mut xlib::XEvent,
+    wanted: *mut c_char,
+) -> c_int {
+    let wanted = *(wanted as *const (xlib::Window, xlib::Atom, xlib::Atom));
+    let xlib::XSelectionEvent {
+        requestor,
+        selection,
+        target,
+        ..
+    } = (*event).selection;
+
+    ((*event).get_type() == xlib::SelectionNotify && (requestor, selection, target) == wanted) as _
+}
+
+unsafe fn intern_atom(display: *mut xlib::Display, name: &CStr) -> xlib::Atom {
+    XInternAtom(display, name.as_ptr(), 0)
+}
+
+unsafe fn store_title(display: *mut xlib::Display, window: xlib::Window, title: &CStr) {
+    let net_wm_name = intern_atom(display, c"_NET_WM_NAME");
+    let utf8_string = intern_atom(display, c"UTF8_STRING");
+    let bytes = title.to_bytes();
+
+    // `WM_NAME` is Latin-1 and only kept for ancient window managers.
+    XStoreName(display, window, title.as_ptr());
+
+    XChangeProperty(
+        display,
+        window,
+        net_wm_name,
+        utf8_string,
+        8,
+        xlib::PropModeReplace,
+        bytes.as_ptr(),
+        bytes.len() as _,
+    );
+}
+
+fn translate_button(button: c_uint) -> Option<Button> {
+    match button {
+        xlib::Button1 => Some(Button::Left),
+        xlib::Button2 => Some(Button::Middle),
+        xlib::Button3 => Some(Button::Right),
+        8 => Some(Button::Back),
+        9 => Some(Button::Forward),
+        _ => None,
+    }
+}
@@ -0,0 +1,153 @@
+use core::{
+    ffi::{c_int, CStr},
+    ptr, slice,
+};
+
+use x11::{
+    xlib::{self, XDefaultRootWindow, XResourceManagerString},
+    xrandr::{
+        self, XRRFreeCrtcInfo, XRRFreeOutputInfo, XRRFreeScreenResources, XRRGetCrtcInfo,
+        XRRGetOutputInfo, XRRGetOutputPrimary, XRRGetScreenResourcesCurrent, XRRQueryExtension,
+        XRRSelectInput,
+    },
+};
+
+use crate::window::Monitor;
+
+/// Ask to be notified about monitor changes, returning the base event code of the extension.
+pub unsafe fn select_changes(display: *mut xlib::Display, window: xlib::Window) -> Option<c_int> {
+    let (mut event_base, mut error_base) = (0, 0);
+
+    if XRRQueryExtension(display, &mut event_base, &mut error_base) == 0 {
+        return None;
+    }
+
+    // Changes to the screen size alone miss modes changing or outputs coming and going within it.
+    let mask = xrandr::RRScreenChangeNotifyMask
+        | xrandr::RRCrtcChangeNotifyMask
+        | xrandr::RROutputChangeNotifyMask;
+
+    XRRSelectInput(display, window, mask);
+    Some(event_base)
+}
+
+pub unsafe fn query(display: *mut xlib::Display) -> Vec<Monitor> {
+    let root = XDefaultRootWindow(display);
+    let resources = XRRGetScreenResourcesCurrent(display, root);
+
+    if resources.is_null() {
+        return Vec::new();
+    }
+
+    let outputs = slice::from_raw_parts((*resources).outputs, (*resources).noutput as _);
+    let modes = slice::from_raw_parts((*resources).modes, (*resources).nmode as _);
+    let primary = XRRGetOutputPrimary(display, root);
+    let xft_dpi = xft_dpi(display);
+    let mut monitors = Vec::new();
+
+    for &output in outputs {
+        let output_info = XRRGetOutputInfo(display, resources, output);
+
+        if output_info.is_null() {
+            continue;
+        }
+
+        let xrandr::XRROutputInfo {
+            crtc,
+            name,
+            nameLen,
+            mm_width,
+            mm_height,
+            connection,
+            ..
+        } = *output_info;
+
+        // Outputs without a CRTC are connected but turned off.
+        let crtc_info = match (connection as c_int, crtc) {
+            (xrandr::RR_Connected, 1..) => XRRGetCrtcInfo(display, resources, crtc),
+            _ => ptr::null_mut(),
+        };
+
+        if !crtc_info.is_null() {
+            let xrandr::XRRCrtcInfo {
+                x,
+                y,
+                width,
+                height,
+                mode,
+                ..
+            } = *crtc_info;
+
+            let name = slice::from_raw_parts(name as *const u8, nameLen as _);
+            let size = [width, height];
+            let physical_size = [mm_width as _, mm_height as _];
+
+            let scale_factor = match xft_dpi {
+                Some(dpi) => dpi / 96.0,
+                None => physical_scale_factor(size, physical_size),
+            };
+
+            monitors.push(Monitor {
+                name: String::from_utf8_lossy(name).into_owned(),
+                position: [x, y],
+                size,
+                physical_size,
+                refresh_rate: modes
+                    .iter()
+                    .find(|info| info.id == mode)
+                    .and_then(refresh_rate),
+                scale_factor,
+                primary: output == primary,
+            });
+
+            XRRFreeCrtcInfo(crtc_info);
+        }
+
+        XRRFreeOutputInfo(output_info);
+    }
+
+    XRRFreeScreenResources(resources);
+    monitors
+}
+
+/// The DPI configured for the whole desktop, which takes precedence over the physical one.
+unsafe fn xft_dpi(display: *mut xlib::Display) -> Option<f64> {
+    let resources = XResourceManagerString(display);
+
+    if resources.is_null() {
+        return None;
+    }
+
+    CStr::from_ptr(resources)
+        .to_str()
+        .ok()?
+        .lines()
+        .find_map(|line| line.strip_prefix("Xft.dpi:"))
+        .and_then(|dpi| dpi.trim().parse().ok())
+}
+
+fn physical_scale_factor([width, _]: [u32; 2], [physical_width, _]: [u32; 2]) -> f64 {
+    if physical_width == 0 {
+        return 1.0;
+    }
+
+    let dpi = width as f64 * 25.4 / physical_width as f64;
+
+    // Some monitors report nonsense (like their aspect ratio) as size, stick to sensible steps.
+    (dpi / 96.0 * 4.0).round().clamp(4.0, 16.0) / 4.0
+}
+
+fn refresh_rate(mode: &xrandr::XRRModeInfo) -> Option<f64> {
+    let mut lines = mode.vTotal as f64;
+
+    if mode.modeFlags & xrandr::RR_DoubleScan as xrandr::XRRModeFlags != 0 {
+        lines *= 2.0;
+    }
+
+    if mode.modeFlags & xrandr::RR_Interlace as xrandr::XRRModeFlags != 0 {
+        lines /= 2.0;
+    }
+
+    let dots = mode.hTotal as f64 * lines;
+    (dots > 0.0).then(|| mode.dotClock as f64 / dots)
+}
@@ -0,0 +1,83 @@
+use core::{ffi::c_int, mem, slice};
+
+use x11::{
+    xinput2::{self, XIQueryVersion, XISelectEvents},
+    xlib::{
+        self, XCreateBitmapFromData, XCreatePixmapCursor, XDefaultRootWindow, XFreeEventData,
+        XFreePixmap, XGetEventData, XQueryExtension,
+    },
+};
+
+/// Major opcode of XInput if the server supports raw events, which need version 2.0.
+pub unsafe fn query_raw_motion(display: *mut xlib::Display) -> Option<c_int> {
+    let (mut opcode, mut event, mut error) = (0, 0, 0);
+    let name = c"XInputExtension".as_ptr();
+
+    if XQueryExtension(display, name, &mut opcode, &mut event, &mut error) == 0 {
+        return None;
+    }
+
+    let (mut major, mut minor) = (2, 0);
+    let status = XIQueryVersion(display, &mut major, &mut minor);
+
+    (status == xlib::Success as _).then_some(opcode)
+}
+
+/// Raw events are only delivered to the root window, regardless of where the pointer is.
+pub unsafe fn select_raw_motion(display: *mut xlib::Display, enable: bool) {
+    let mut mask = [0; (xinput2::XI_LASTEVENT as usize).div_ceil(8)];
+
+    if enable {
+        xinput2::XISetMask(&mut mask, xinput2::XI_RawMotion);
+    }
+
+    let mut event_mask = xinput2::XIEventMask {
+        deviceid: xinput2::XIAllMasterDevices,
+        mask_len: mask.len() as _,
+        mask: mask.as_mut_ptr(),
+    };
+
+    XISelectEvents(display, XDefaultRootWindow(display), &mut event_mask, 1);
+}
+
+pub unsafe fn read_raw_motion(
+    display: *mut xlib::Display,
+    cookie: &mut xlib::XGenericEventCookie,
+) -> Option<[f64; 2]> {
+    if cookie.evtype != xinput2::XI_RawMotion || XGetEventData(display, cookie) == 0 {
+        return None;
+    }
+
+    let raw_event = &*(cookie.data as *const xinput2::XIRawEvent);
+    let valuators = &raw_event.valuators;
+    let mask = slice::from_raw_parts(valuators.mask, valuators.mask_len as _);
+    let mut values = raw_event.raw_values;
+    let mut delta = [0.0; 2];
+
+    // Only the valuators set in the mask carry a value, packed in order.
+    for (axis, delta) in delta.iter_mut().enumerate() {
+        if mask
+            .get(axis / 8)
+            .is_some_and(|byte| byte & (1 << (axis % 8)) != 0)
+        {
+            *delta = *values;
+            values = values.add(1);
+        }
+    }
+
+    XFreeEventData(display, cookie);
+    Some(delta)
+}
+
+pub unsafe fn create_invisible_cursor(
+    display: *mut xlib::Display,
+    window: xlib::Window,
+) -> xlib::Cursor {
+    let data = 0;
+    let pixmap = XCreateBitmapFromData(display, window, &data, 1, 1);
+    let mut color = mem::zeroed();
+    let cursor = XCreatePixmapCursor(display, pixmap, pixmap, &mut color, &mut color, 0, 0);
+
+    XFreePixmap(display, pixmap);
+    cursor
+}
@@ -0,0 +1,75 @@
+use core::{
+    ffi::c_int,
+    mem, ptr,
+    sync::atomic::{AtomicBool, AtomicI32, Ordering},
+};
+use std::sync::Once;
+
+// Signals interrupt whatever the process is doing, the handler can only leave a note behind.
+static RECEIVED: AtomicBool = AtomicBool::new(false);
+
+// The handler writes to a pipe too, to wake up whoever is waiting on the other end.
+static READ_END: AtomicI32 = AtomicI32::new(-1);
+static WRITE_END: AtomicI32 = AtomicI32::new(-1);
+
+pub fn install_handlers() {
+    static INSTALL: Once = Once::new();
+
+    INSTALL.call_once(|| unsafe {
+        let mut fds = [-1; 2];
+
+        if libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC | libc::O_NONBLOCK) == 0 {
+            READ_END.store(fds[0], Ordering::Relaxed);
+            WRITE_END.store(fds[1], Ordering::Relaxed);
+        }
+
+        let mut action = mem::zeroed::<libc::sigaction>();
+
+        action.sa_sigaction = on_signal as extern "C" fn(c_int) as libc::sighandler_t;
+
+        // A second signal terminates the process as usual, in case it got stuck somewhere.
+        action.sa_flags = libc::SA_RESTART | libc::SA_RESETHAND;
+        libc::sigemptyset(&mut action.sa_mask);
+
+        for signal in [libc::SIGINT, libc::SIGTERM] {
+            libc::sigaction(signal, &action, ptr::null_mut());
+        }
+    });
+}
+
+/// Becomes readable when a signal arrives, until it is taken.
+pub fn wake_up_fd() -> Option<c_int> {
+    let fd = READ_END.load(Ordering::Relaxed);
+    (fd >= 0).then_some(fd)
+}
+
+pub fn is_pending() -> bool {
+    RECEIVED.load(Ordering::Acquire)
+}
+
+pub fn take_signal() -> bool {
+    if !RECEIVED.swap(false, Ordering::AcqRel) {
+        return false;
+    }
+
+    let mut buffer = [0_u8; 16];
+
+    // Empty the pipe, or it would keep waking everyone up.
+    if let Some(fd) = wake_up_fd() {
+        while unsafe { libc::read(fd, buffer.as_mut_ptr() as _, buffer.len()) } > 0 {}
+    }
+
+    true
+}
+
+extern "C" fn on_signal(_: c_int) {
+    RECEIVED.store(true, Ordering::Release);
+
+    let fd = WRITE_END.load(Ordering::Relaxed);
+    let byte = 0_u8;
+
+    // Writing to a pipe is one of the few things that are safe to do from a signal handler.
+    if fd >= 0 {
+        unsafe { libc::write(fd, &byte as *const _ as _, 1) };
+    }
+}
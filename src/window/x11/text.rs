@@ -0,0 +1,133 @@
+use core::{
+    ffi::{c_long, c_ulong, c_void},
+    ptr,
+};
+
+use x11::xlib::{
+    self, XCloseIM, XCreateIC, XDestroyIC, XGetICValues, XOpenIM, XSetICFocus, XSetLocaleModifiers,
+    XUnsetICFocus, Xutf8LookupString,
+};
+
+/// One per connection, shared by the input contexts of every window.
+pub struct InputMethod {
+    im: xlib::XIM,
+}
+
+pub struct InputContext {
+    ic: xlib::XIC,
+}
+
+impl InputMethod {
+    pub unsafe fn open(display: *mut xlib::Display) -> Option<Self> {
+        // Input methods are chosen by locale, which is left as "C" unless set explicitly.
+        libc::setlocale(libc::LC_CTYPE, c"".as_ptr());
+        XSetLocaleModifiers(c"".as_ptr());
+
+        let mut im = XOpenIM(display, ptr::null_mut(), ptr::null_mut(), ptr::null_mut());
+
+        // Without an input method server, Xlib's built-in one still handles dead keys and compose.
+        if im.is_null() {
+            XSetLocaleModifiers(c"@im=none".as_ptr());
+            im = XOpenIM(display, ptr::null_mut(), ptr::null_mut(), ptr::null_mut());
+        }
+
+        if im.is_null() {
+            return None;
+        }
+
+        Some(Self { im })
+    }
+
+    pub unsafe fn create_context(&self, window: xlib::Window) -> Option<InputContext> {
+        let style = xlib::XIMPreeditNothing | xlib::XIMStatusNothing;
+
+        let ic = XCreateIC(
+            self.im,
+            xlib::XNInputStyle_0.as_ptr(),
+            style as c_ulong,
+            xlib::XNClientWindow_0.as_ptr(),
+            window,
+            xlib::XNFocusWindow_0.as_ptr(),
+            window,
+            ptr::null_mut::<c_void>(),
+        );
+
+        if ic.is_null() {
+            return None;
+        }
+
+        XUnsetICFocus(ic);
+        Some(InputContext { ic })
+    }
+
+    /// Must happen after every input context is destroyed, and before the display is closed.
+    pub unsafe fn close(&self) {
+        XCloseIM(self.im);
+    }
+}
+
+impl InputContext {
+    /// Events the input method needs on top of the ones selected for the window.
+    pub unsafe fn event_mask(&self) -> c_long {
+        let mut mask: c_long = 0;
+
+        XGetICValues(
+            self.ic,
+            xlib::XNFilterEvents_0.as_ptr(),
+            &mut mask,
+            ptr::null_mut::<c_void>(),
+        );
+
+        mask
+    }
+
+    pub unsafe fn set_focus(&self, focused: bool) {
+        match focused {
+            true => XSetICFocus(self.ic),
+            false => XUnsetICFocus(self.ic),
+        }
+    }
+
+    pub unsafe fn lookup(&self, key_event: &mut xlib::XKeyEvent) -> Option<String> {
+        let mut buffer = vec![0_u8; 64];
+        let mut keysym = 0;
+        let mut status = 0;
+
+        let length = loop {
+            let length = Xutf8LookupString(
+                self.ic,
+                key_event,
+                buffer.as_mut_ptr() as _,
+                buffer.len() as _,
+                &mut keysym,
+                &mut status,
+            );
+
+            // The returned length is the one the text needs, try again with enough room for it.
+            if status != xlib::XBufferOverflow {
+                break length;
+            }
+
+            buffer.resize(length as _, 0);
+        };
+
+        if !matches!(status, xlib::XLookupChars | xlib::XLookupBoth) {
+            return None;
+        }
+
+        buffer.truncate(length as _);
+
+        // Keys like Enter or Backspace produce control characters, but they are reported as keys.
+        let text = String::from_utf8(buffer).ok()?;
+        let text = text
+            .chars()
+            .filter(|char| !char.is_control())
+            .collect::<String>();
+
+        (!text.is_empty()).then_some(text)
+    }
+
+    pub unsafe fn destroy(&self) {
+        XDestroyIC(self.ic);
+    }
+}